@@ -8,32 +8,43 @@
 #define NENV (1 << LOG2NENV)
 #define ENVX(envid) ((envid) & (NENV - 1))
 
-// All possible values of 'env_status' in 'struct Env'.
+// All possible values of 'env_status' in 'struct EnvInfo'.
 #define ENV_FREE 0
 #define ENV_RUNNABLE 1
 #define ENV_NOT_RUNNABLE 2
 
-struct Env {
-	struct Trapframe env_tf;	 // saved context (registers) before switching
-	u_int env_id;			 // unique environment identifier
-	u_int env_asid;			 // ASID of this env
-	u_int env_parent_id;		 // env_id of this env's parent
-	u_int env_status;		 // status of this env
-	Pde *env_pgdir;			 // page directory
-	u_int env_pri;			 // schedule priority
+#define ENV_INFO_VERSION 1
 
-	// Lab 4 IPC
+// Read-only record of an env, mapped by the kernel at UENVS.
+struct EnvInfo {
+	u_int version;	       // layout version, ENV_INFO_VERSION
+	u_int env_id;	       // unique environment identifier
+	u_int env_parent_id;   // env_id of this env's parent
+	u_int env_status;      // status of this env
+	u_int env_pri;	       // schedule priority
+	u_int env_runs;	       // number of times we've been env_run'ed
+	int env_exit_code;     // exit code, valid once env_status is ENV_FREE
 	u_int env_ipc_value;   // the value sent to us
 	u_int env_ipc_from;    // envid of the sender
-	u_int env_ipc_recving; // whether this env is blocked receiving
-	u_int env_ipc_dstva;   // va at which the received page should be mapped
 	u_int env_ipc_perm;    // perm in which the received page should be mapped
+	u_int reserved[6];
+};
 
-	// Lab 4 fault handling
-	u_int env_user_tlb_mod_entry; // userspace TLB Mod handler
+#define ENV_NAME_LEN 16
+#define ENV_CMDLINE_MAX 256
 
-	// Lab 6 scheduler counts
-	u_int env_runs; // number of times we've been env_run'ed
+// Snapshot of an env returned by syscall_env_stat.
+struct EnvStat {
+	u_int env_id;		 // unique environment identifier
+	u_int env_parent_id;	 // env_id of this env's parent
+	u_int env_status;	 // status of this env
+	u_int env_pri;		 // schedule priority
+	char env_name[ENV_NAME_LEN]; // nul terminated name
+	u_int env_runs;		 // number of times we've been env_run'ed
+	u_int env_ticks;	 // cpu time in clock ticks
+	u_int env_pages;	 // resident user pages
+	u_int env_ipc_recving;	 // whether this env is blocked receiving
+	u_int env_sleeping;	 // whether this env is blocked in a syscall
 };
 
 #endif // !_ENV_H_
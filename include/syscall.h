@@ -30,6 +30,41 @@ enum {
 	SYS_semwait,
 	SYS_sempost,
 	SYS_semkill,
+	SYS_console_read,
+	SYS_tty_ioctl,
+	SYS_env_notify,
+	SYS_set_notify_entry,
+	SYS_time_of_day,
+	SYS_set_time_of_day,
+	SYS_get_trapframe,
+	SYS_env_list,
+	SYS_env_stat,
+	SYS_getrusage,
+	SYS_get_rlimit,
+	SYS_set_rlimit,
+	SYS_set_cmdline,
+	SYS_get_cmdline,
+	SYS_set_pgid,
+	SYS_get_pgid,
+	SYS_set_sid,
+	SYS_get_sid,
+	SYS_group_notify,
+	SYS_group_destroy,
+	SYS_env_destroy_tree,
+	SYS_set_teardown,
+	SYS_ptrace,
+	SYS_core_info,
+	SYS_core_read,
+	SYS_core_clear,
+	SYS_strace,
+	SYS_strace_read,
+	SYS_klog_read,
+	SYS_prof_start,
+	SYS_prof_stop,
+	SYS_prof_read,
+	SYS_tlb_read,
+	SYS_memcheck,
+	SYS_core_wait,
 	MAX_SYSNO,
 };
 
@@ -0,0 +1,183 @@
+use alloc::vec::Vec;
+
+use crate::{env::{env_check_cpu_limit, env_sched}, sync::cell::UPSafeCell};
+
+extern "C" {
+    fn read_cp0_count() -> u32;
+    fn write_cp0_compare(compare: u32);
+}
+
+/// frequency of cp0 count on malta
+pub const CLOCK_FREQ: u64 = 100_000_000;
+/// default tick rate
+pub const DEFAULT_HZ: usize = 200;
+/// lowest tick rate accepted at boot
+pub const MIN_HZ: usize = 10;
+/// highest tick rate accepted at boot
+pub const MAX_HZ: usize = 10000;
+/// least cycles between now and compare, or the interrupt may be missed
+const MIN_DELTA: u64 = 1000;
+/// longest idle sleep, count must be read before it wraps
+const MAX_IDLE: u64 = 1 << 31;
+
+/// global clock
+pub static CLOCK: UPSafeCell<Clock> = UPSafeCell::new(Clock::new());
+
+/// one-shot timer
+#[derive(Clone, Copy)]
+struct Timer {
+    deadline: u64,
+    handler: fn(usize),
+    arg: usize
+}
+
+/// owner of cp0 count and compare
+pub struct Clock {
+    hz: usize,
+    interval: u64,
+    high: u64,
+    last: u32,
+    tick_deadline: Option<u64>,
+    timers: Vec<Timer>
+}
+
+impl Clock {
+    /// create a clock with default tick rate
+    pub const fn new() -> Self {
+        Self {
+            hz: DEFAULT_HZ,
+            interval: CLOCK_FREQ / DEFAULT_HZ as u64,
+            high: 0,
+            last: 0,
+            tick_deadline: None,
+            timers: Vec::new()
+        }
+    }
+
+    /// set tick rate, out of range values are clamped
+    pub fn set_hz(&mut self, hz: usize) {
+        self.hz = hz.clamp(MIN_HZ, MAX_HZ);
+        self.interval = CLOCK_FREQ / self.hz as u64;
+    }
+
+    /// cycles since boot, extended to 64 bits
+    pub fn now(&mut self) -> u64 {
+        let count = unsafe { read_cp0_count() };
+        if count < self.last {
+            self.high += 1 << 32;
+        }
+        self.last = count;
+        self.high | count as u64
+    }
+
+    /// nearest deadline of timers
+    #[inline]
+    fn nearest(&self) -> Option<u64> {
+        self.timers.first().map(|t| t.deadline)
+    }
+
+    /// program compare for deadline
+    fn program(&mut self, deadline: u64) {
+        let now = self.now();
+        let deadline = deadline.clamp(now + MIN_DELTA, now + MAX_IDLE);
+        unsafe { write_cp0_compare(deadline as u32) };
+    }
+
+    /// program compare for the earlier of tick deadline and nearest timer
+    fn reprogram(&mut self) {
+        let deadline = match (self.tick_deadline, self.nearest()) {
+            (Some(tick), Some(timer)) => tick.min(timer),
+            (Some(tick), None) => tick,
+            (None, Some(timer)) => timer,
+            (None, None) => u64::MAX
+        };
+        self.program(deadline);
+    }
+
+    /// start a new time slice
+    pub fn arm_tick(&mut self) {
+        self.tick_deadline = Some(self.now() + self.interval);
+        self.reprogram();
+    }
+
+    /// stop ticking while idle, only wake up for the nearest timer
+    pub fn arm_idle(&mut self) {
+        self.tick_deadline = None;
+        self.reprogram();
+    }
+
+    /// add a timer firing after us microseconds
+    pub fn add_timer(&mut self, us: u64, handler: fn(usize), arg: usize) {
+        let deadline = self.now() + us * (CLOCK_FREQ / 1_000_000);
+        let pos = self.timers.partition_point(|t| t.deadline <= deadline);
+        self.timers.insert(pos, Timer { deadline, handler, arg });
+    }
+
+    /// remove expired timers
+    fn expire(&mut self) -> Vec<Timer> {
+        let now = self.now();
+        let n = self.timers.partition_point(|t| t.deadline <= now);
+        self.timers.drain(..n).collect()
+    }
+}
+
+/// set tick rate and start the clock
+pub fn clock_init(hz: usize) {
+    let mut clock = CLOCK.borrow_mut();
+    clock.set_hz(hz);
+    clock.now();
+}
+
+/// current tick rate
+#[inline]
+pub fn clock_hz() -> usize {
+    CLOCK.borrow_mut().hz
+}
+
+/// cycles since boot
+#[inline]
+pub fn uptime_cycles() -> u64 {
+    CLOCK.borrow_mut().now()
+}
+
+/// ticks since boot
+#[inline]
+pub fn uptime_ticks() -> u64 {
+    cycles_to_ticks(uptime_cycles())
+}
+
+/// convert cycles to ticks of current tick rate
+#[inline]
+pub fn cycles_to_ticks(cycles: u64) -> u64 {
+    cycles / CLOCK.borrow_mut().interval
+}
+
+/// microseconds since boot
+#[inline]
+pub fn uptime_us() -> u64 {
+    uptime_cycles() / (CLOCK_FREQ / 1_000_000)
+}
+
+/// add a timer firing after us microseconds
+#[inline]
+pub fn add_timer(us: u64, handler: fn(usize), arg: usize) {
+    CLOCK.borrow_mut().add_timer(us, handler, arg);
+}
+
+/// timer irq handler, fire expired timers and reschedule at the end of time slice
+pub fn clock_intr() {
+    let expired = CLOCK.borrow_mut().expire();
+    for timer in expired {
+        (timer.handler)(timer.arg);
+    }
+    let mut clock = CLOCK.borrow_mut();
+    let now = clock.now();
+    match clock.tick_deadline {
+        Some(deadline) if now < deadline => clock.reprogram(),
+        _ => {
+            drop(clock);
+            env_check_cpu_limit();
+            env_sched(0);
+        }
+    }
+}
@@ -1,8 +1,38 @@
-use core::ptr::copy;
+use core::ptr::{copy, read_volatile, write_volatile};
 
 use crate::memory::mmu::{PhysAddr, VirtAddr, KSEG1};
 
 pub mod malta;
+/// 16550 serial driver
+pub mod serial;
+/// i8259 interrupt controller
+pub mod i8259;
+/// console tty
+pub mod tty;
+/// mc146818 real-time clock
+pub mod rtc;
+
+/// read a byte from device register
+#[inline]
+pub fn ioread8(pa: usize) -> u8 {
+    unsafe { read_volatile((KSEG1 + pa) as *const u8) }
+}
+/// write a byte to device register
+#[inline]
+pub fn iowrite8(pa: usize, v: u8) {
+    unsafe { write_volatile((KSEG1 + pa) as *mut u8, v) }
+}
+/// read a word from device register
+#[inline]
+pub fn ioread32(pa: usize) -> u32 {
+    unsafe { read_volatile((KSEG1 + pa) as *const u32) }
+}
+/// power off the board through the fpga, qemu exits if started with `-no-reboot`
+pub fn halt() -> ! {
+    serial::serial_flush();
+    iowrite8(malta::MALTA_FPGA_HALT, malta::MALTA_FPGA_HALT_MAGIC);
+    loop {}
+}
 
 /// device manager struct
 pub struct DeviceManager;
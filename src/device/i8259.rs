@@ -0,0 +1,91 @@
+use super::{ioread32, ioread8, iowrite8, malta::*};
+
+/// icw1: edge triggered, cascade mode, icw4 needed
+const ICW1_INIT: u8 = 0x11;
+/// icw4: 8086 mode
+const ICW4_8086: u8 = 0x01;
+/// vector base of master
+const MASTER_BASE: u8 = 0;
+/// vector base of slave
+const SLAVE_BASE: u8 = 8;
+
+/// init the i8259 pair, all irqs are masked
+pub fn init() {
+    iowrite8(MALTA_I8259_MASTER_DATA, 0xff);
+    iowrite8(MALTA_I8259_SLAVE_DATA, 0xff);
+
+    iowrite8(MALTA_I8259_MASTER_CMD, ICW1_INIT);
+    iowrite8(MALTA_I8259_MASTER_DATA, MASTER_BASE);
+    iowrite8(MALTA_I8259_MASTER_DATA, 1 << MALTA_I8259_CASCADE_IRQ);
+    iowrite8(MALTA_I8259_MASTER_DATA, ICW4_8086);
+
+    iowrite8(MALTA_I8259_SLAVE_CMD, ICW1_INIT);
+    iowrite8(MALTA_I8259_SLAVE_DATA, SLAVE_BASE);
+    iowrite8(MALTA_I8259_SLAVE_DATA, MALTA_I8259_CASCADE_IRQ as u8);
+    iowrite8(MALTA_I8259_SLAVE_DATA, ICW4_8086);
+
+    iowrite8(MALTA_I8259_MASTER_DATA, !(1 << MALTA_I8259_CASCADE_IRQ));
+    iowrite8(MALTA_I8259_SLAVE_DATA, 0xff);
+}
+
+/// unmask an irq
+pub fn enable(irq: usize) {
+    if irq < 8 {
+        let mask = ioread8(MALTA_I8259_MASTER_DATA);
+        iowrite8(MALTA_I8259_MASTER_DATA, mask & !(1 << irq));
+    } else {
+        let mask = ioread8(MALTA_I8259_SLAVE_DATA);
+        iowrite8(MALTA_I8259_SLAVE_DATA, mask & !(1 << (irq - 8)));
+    }
+}
+
+/// mask an irq
+pub fn disable(irq: usize) {
+    if irq < 8 {
+        let mask = ioread8(MALTA_I8259_MASTER_DATA);
+        iowrite8(MALTA_I8259_MASTER_DATA, mask | (1 << irq));
+    } else {
+        let mask = ioread8(MALTA_I8259_SLAVE_DATA);
+        iowrite8(MALTA_I8259_SLAVE_DATA, mask | (1 << (irq - 8)));
+    }
+}
+
+/// get the pending irq through the gt-64120 interrupt acknowledge cycle
+#[inline]
+pub fn ack() -> usize {
+    (ioread32(MALTA_GT_PCI0_IACK) & 0xff) as usize
+}
+
+/// send end of interrupt
+#[inline]
+pub fn eoi(irq: usize) {
+    if irq >= 8 {
+        iowrite8(MALTA_I8259_SLAVE_CMD, MALTA_I8259_EOI);
+    }
+    iowrite8(MALTA_I8259_MASTER_CMD, MALTA_I8259_EOI);
+}
+
+/// check the in-service register of the controller owning irq
+#[inline]
+fn in_service(irq: usize) -> bool {
+    let cmd = if irq < 8 { MALTA_I8259_MASTER_CMD } else { MALTA_I8259_SLAVE_CMD };
+    iowrite8(cmd, MALTA_I8259_READ_ISR);
+    ioread8(cmd) & (1 << (irq & 7)) != 0
+}
+
+/// acknowledge the pending irq and send eoi, return None if it is spurious.
+/// a spurious irq 7 needs no eoi, a spurious irq 15 still needs one on master.
+pub fn pending() -> Option<usize> {
+    let irq = ack();
+    if irq >= MALTA_I8259_NR_IRQS {
+        return None;
+    }
+    if irq & 7 == MALTA_I8259_SPURIOUS_IRQ && !in_service(irq) {
+        if irq >= 8 {
+            iowrite8(MALTA_I8259_MASTER_CMD, MALTA_I8259_EOI);
+        }
+        return None;
+    }
+    eoi(irq);
+    Some(irq)
+}
\ No newline at end of file
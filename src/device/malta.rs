@@ -9,4 +9,116 @@ pub const MALTA_SERIAL_LSR: usize = MALTA_SERIAL_BASE + 0x5;
 /// register constant
 pub const MALTA_SERIAL_THR_EMPTY: u8 = 0x20;
 /// register constant
-pub const MALTA_SERIAL_DATA_READY: u8 = 0x1;
\ No newline at end of file
+pub const MALTA_SERIAL_DATA_READY: u8 = 0x1;
+/// register constant
+pub const MALTA_SERIAL_IER: usize = MALTA_SERIAL_BASE + 0x1;
+/// register constant
+pub const MALTA_SERIAL_IIR: usize = MALTA_SERIAL_BASE + 0x2;
+/// register constant
+pub const MALTA_SERIAL_FCR: usize = MALTA_SERIAL_BASE + 0x2;
+/// register constant
+pub const MALTA_SERIAL_MCR: usize = MALTA_SERIAL_BASE + 0x4;
+/// register constant
+pub const MALTA_SERIAL_IER_RDI: u8 = 0x1;
+/// register constant
+pub const MALTA_SERIAL_IER_THRI: u8 = 0x2;
+/// register constant
+pub const MALTA_SERIAL_IIR_NO_INT: u8 = 0x1;
+/// register constant
+pub const MALTA_SERIAL_FCR_ENABLE: u8 = 0x1;
+/// register constant
+pub const MALTA_SERIAL_FCR_CLEAR: u8 = 0x6;
+/// register constant
+pub const MALTA_SERIAL_MCR_DTR: u8 = 0x1;
+/// register constant
+pub const MALTA_SERIAL_MCR_RTS: u8 = 0x2;
+/// register constant
+pub const MALTA_SERIAL_MCR_OUT2: u8 = 0x8;
+/// serial fifo depth
+pub const MALTA_SERIAL_FIFO_SIZE: usize = 16;
+/// serial irq on i8259
+pub const MALTA_SERIAL_IRQ: usize = 4;
+
+/// second serial port, used by the gdb stub
+pub const MALTA_COM2_BASE: usize = MALTA_PCIIO_BASE + 0x2f8;
+/// register constant
+pub const MALTA_COM2_DATA: usize = MALTA_COM2_BASE + 0x0;
+/// register constant
+pub const MALTA_COM2_IER: usize = MALTA_COM2_BASE + 0x1;
+/// register constant
+pub const MALTA_COM2_FCR: usize = MALTA_COM2_BASE + 0x2;
+/// register constant
+pub const MALTA_COM2_MCR: usize = MALTA_COM2_BASE + 0x4;
+/// register constant
+pub const MALTA_COM2_LSR: usize = MALTA_COM2_BASE + 0x5;
+/// second serial irq on i8259
+pub const MALTA_COM2_IRQ: usize = 3;
+
+/// register constant
+pub const MALTA_I8259_MASTER_CMD: usize = MALTA_PCIIO_BASE + 0x20;
+/// register constant
+pub const MALTA_I8259_MASTER_DATA: usize = MALTA_PCIIO_BASE + 0x21;
+/// register constant
+pub const MALTA_I8259_SLAVE_CMD: usize = MALTA_PCIIO_BASE + 0xa0;
+/// register constant
+pub const MALTA_I8259_SLAVE_DATA: usize = MALTA_PCIIO_BASE + 0xa1;
+/// register constant
+pub const MALTA_I8259_CASCADE_IRQ: usize = 2;
+/// register constant
+pub const MALTA_I8259_EOI: u8 = 0x20;
+
+/// register constant
+pub const MALTA_GT_BASE: usize = 0x1be00000;
+/// register constant
+pub const MALTA_GT_PCI0_IACK: usize = MALTA_GT_BASE + 0xc34;
+
+/// register constant
+pub const MALTA_I8259_READ_ISR: u8 = 0x0b;
+/// spurious irq reported by master
+pub const MALTA_I8259_SPURIOUS_IRQ: usize = 7;
+/// number of irq lines on i8259 pair
+pub const MALTA_I8259_NR_IRQS: usize = 16;
+
+/// cpu interrupt line of i8259
+pub const MALTA_CPU_PIC_IRQ: usize = 2;
+/// cpu interrupt line of cp0 timer
+pub const MALTA_CPU_TIMER_IRQ: usize = 7;
+
+/// register constant
+pub const MALTA_RTC_BASE: usize = MALTA_PCIIO_BASE + 0x70;
+/// register constant
+pub const MALTA_RTC_ADDR: usize = MALTA_RTC_BASE + 0x0;
+/// register constant
+pub const MALTA_RTC_DATA: usize = MALTA_RTC_BASE + 0x1;
+/// register constant
+pub const MALTA_RTC_SEC: u8 = 0x00;
+/// register constant
+pub const MALTA_RTC_MIN: u8 = 0x02;
+/// register constant
+pub const MALTA_RTC_HOUR: u8 = 0x04;
+/// register constant
+pub const MALTA_RTC_DAY: u8 = 0x07;
+/// register constant
+pub const MALTA_RTC_MONTH: u8 = 0x08;
+/// register constant
+pub const MALTA_RTC_YEAR: u8 = 0x09;
+/// register constant
+pub const MALTA_RTC_REG_A: u8 = 0x0a;
+/// register constant
+pub const MALTA_RTC_REG_B: u8 = 0x0b;
+/// register constant
+pub const MALTA_RTC_CENTURY: u8 = 0x32;
+/// register constant
+pub const MALTA_RTC_A_UIP: u8 = 0x80;
+/// register constant
+pub const MALTA_RTC_B_24H: u8 = 0x02;
+/// register constant
+pub const MALTA_RTC_B_BINARY: u8 = 0x04;
+/// register constant
+pub const MALTA_RTC_HOUR_PM: u8 = 0x80;
+/// register constant
+pub const MALTA_FPGA_BASE: usize = 0x1f000000;
+/// register constant
+pub const MALTA_FPGA_HALT: usize = MALTA_FPGA_BASE + 0x500;
+/// value written to the halt register to power off
+pub const MALTA_FPGA_HALT_MAGIC: u8 = 0x42;
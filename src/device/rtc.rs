@@ -0,0 +1,144 @@
+use crate::{clock::uptime_us, sync::cell::UPSafeCell};
+
+use super::{ioread8, iowrite8, malta::*};
+
+/// microseconds per second
+const USEC_PER_SEC: u64 = 1_000_000;
+
+/// wall-clock time since unix epoch
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct TimeVal {
+    pub sec: u32,
+    pub usec: u32,
+}
+
+/// global real-time clock
+pub static RTC: UPSafeCell<Rtc> = UPSafeCell::new(Rtc::new());
+
+/// read rtc once at boot
+pub fn rtc_init() { RTC.borrow_mut().init(); }
+/// current wall-clock time
+#[inline]
+pub fn time_of_day() -> TimeVal { RTC.borrow_mut().time_of_day() }
+
+/// mc146818 rtc, read once at boot and advanced by cp0 count afterwards
+pub struct Rtc {
+    boot_sec: u64,
+    boot_us: u64,
+    offset: i64,
+}
+
+impl Rtc {
+    /// create a new rtc
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            boot_sec: 0,
+            boot_us: 0,
+            offset: 0
+        }
+    }
+
+    /// read a rtc register
+    #[inline]
+    fn read_reg(reg: u8) -> u8 {
+        iowrite8(MALTA_RTC_ADDR, reg);
+        ioread8(MALTA_RTC_DATA)
+    }
+
+    /// read date registers after an update cycle is done
+    fn read_raw() -> [u8; 7] {
+        while Self::read_reg(MALTA_RTC_REG_A) & MALTA_RTC_A_UIP != 0 {}
+        [
+            Self::read_reg(MALTA_RTC_SEC),
+            Self::read_reg(MALTA_RTC_MIN),
+            Self::read_reg(MALTA_RTC_HOUR),
+            Self::read_reg(MALTA_RTC_DAY),
+            Self::read_reg(MALTA_RTC_MONTH),
+            Self::read_reg(MALTA_RTC_YEAR),
+            Self::read_reg(MALTA_RTC_CENTURY),
+        ]
+    }
+
+    /// read rtc as seconds since unix epoch
+    pub fn read_seconds() -> u64 {
+        let mut raw = Self::read_raw();
+        loop {
+            let again = Self::read_raw();
+            if again == raw {
+                break;
+            }
+            raw = again;
+        }
+        let reg_b = Self::read_reg(MALTA_RTC_REG_B);
+        let decode = |v: u8| if reg_b & MALTA_RTC_B_BINARY != 0 { v as u64 } else { bcd2bin(v) };
+
+        let [sec, min, hour, day, month, year, century] = raw;
+        let mut hour_bin = decode(hour & !MALTA_RTC_HOUR_PM);
+        if reg_b & MALTA_RTC_B_24H == 0 {
+            hour_bin %= 12;
+            if hour & MALTA_RTC_HOUR_PM != 0 {
+                hour_bin += 12;
+            }
+        }
+        let century = match decode(century) {
+            c @ 19..=21 => c,
+            _ => if decode(year) < 70 { 20 } else { 19 }
+        };
+        let year = century * 100 + decode(year);
+        let days = days_from_civil(year, decode(month), decode(day));
+        ((days * 24 + hour_bin) * 60 + decode(min)) * 60 + decode(sec)
+    }
+
+    /// read the rtc and remember the boot time
+    pub fn init(&mut self) {
+        self.boot_sec = Self::read_seconds();
+        self.boot_us = uptime_us();
+    }
+
+    /// microseconds since unix epoch
+    fn now_us(&self) -> u64 {
+        let us = self.boot_sec * USEC_PER_SEC + (uptime_us() - self.boot_us);
+        us.saturating_add_signed(self.offset)
+    }
+
+    /// current wall-clock time
+    pub fn time_of_day(&self) -> TimeVal {
+        let us = self.now_us();
+        TimeVal {
+            sec: (us / USEC_PER_SEC) as u32,
+            usec: (us % USEC_PER_SEC) as u32
+        }
+    }
+
+    /// set wall-clock time, the rtc itself is left untouched
+    pub fn set_time_of_day(&mut self, tv: TimeVal) {
+        let target = tv.sec as u64 * USEC_PER_SEC + tv.usec as u64;
+        let base = self.now_us().saturating_add_signed(-self.offset);
+        self.offset = target as i64 - base as i64;
+    }
+
+    /// difference between wall-clock time and rtc in microseconds
+    #[inline]
+    pub fn offset(&self) -> i64 {
+        self.offset
+    }
+}
+
+/// decode a bcd byte
+#[inline]
+fn bcd2bin(v: u8) -> u64 {
+    ((v >> 4) * 10 + (v & 0xf)) as u64
+}
+
+/// days since 1970-01-01 of a civil date
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y / 400;
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
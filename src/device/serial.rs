@@ -0,0 +1,143 @@
+use crate::{exception::irq::{pic_irq, register_irq}, sync::cell::UPSafeCell, util::ring_buffer::RingBuffer};
+
+use super::{ioread8, iowrite8, malta::*, tty::tty_intr};
+
+/// size of serial rx and tx buffer
+pub const SERIAL_BUF_SIZE: usize = 4096;
+
+/// global serial device
+pub static SERIAL: UPSafeCell<Serial> = UPSafeCell::new(Serial::new());
+
+/// init serial device and enable its receive interrupt
+pub fn serial_init() { SERIAL.borrow_mut().init(); }
+/// put a byte to serial device
+#[inline]
+pub fn serial_putc(byte: u8) { SERIAL.borrow_mut().putc(byte); }
+/// get a byte from rx buffer
+#[inline]
+pub fn serial_getc() -> Option<u8> { SERIAL.borrow_mut().getc() }
+/// write out everything in tx buffer by polling
+#[inline]
+pub fn serial_flush() { SERIAL.borrow_mut().flush(); }
+/// serial interrupt handler, received bytes are passed to tty
+#[inline]
+pub fn serial_intr() {
+    let received = SERIAL.borrow_mut().intr();
+    if received {
+        tty_intr();
+    }
+}
+
+/// interrupt driven 16550 uart
+pub struct Serial {
+    rx: RingBuffer<u8, SERIAL_BUF_SIZE>,
+    tx: RingBuffer<u8, SERIAL_BUF_SIZE>,
+    ier: u8,
+}
+
+impl Serial {
+    /// create a new serial device
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            rx: RingBuffer::new(0),
+            tx: RingBuffer::new(0),
+            ier: 0
+        }
+    }
+
+    /// init the uart, register its irq handler
+    pub fn init(&mut self) {
+        iowrite8(MALTA_SERIAL_IER, 0);
+        iowrite8(MALTA_SERIAL_FCR, MALTA_SERIAL_FCR_ENABLE | MALTA_SERIAL_FCR_CLEAR);
+        iowrite8(MALTA_SERIAL_MCR, MALTA_SERIAL_MCR_DTR | MALTA_SERIAL_MCR_RTS | MALTA_SERIAL_MCR_OUT2);
+        self.set_ier(MALTA_SERIAL_IER_RDI);
+        register_irq(pic_irq(MALTA_SERIAL_IRQ), serial_intr).unwrap();
+    }
+
+    /// update interrupt enable register
+    #[inline]
+    fn set_ier(&mut self, ier: u8) {
+        if self.ier != ier {
+            self.ier = ier;
+            iowrite8(MALTA_SERIAL_IER, ier);
+        }
+    }
+
+    /// check if transmitter holding register is empty
+    #[inline]
+    fn thr_empty() -> bool {
+        ioread8(MALTA_SERIAL_LSR) & MALTA_SERIAL_THR_EMPTY != 0
+    }
+
+    /// check if data is ready in receiver
+    #[inline]
+    fn data_ready() -> bool {
+        ioread8(MALTA_SERIAL_LSR) & MALTA_SERIAL_DATA_READY != 0
+    }
+
+    /// queue a byte for transmission
+    pub fn putc(&mut self, byte: u8) {
+        if byte == b'\n' {
+            self.putc(b'\r');
+        }
+        if self.tx.is_full() {
+            self.flush();
+        }
+        let _ = self.tx.push(byte);
+        self.start_tx();
+    }
+
+    /// move bytes from tx buffer to uart fifo, enable thr interrupt if anything left
+    fn start_tx(&mut self) {
+        if Self::thr_empty() {
+            for _ in 0..MALTA_SERIAL_FIFO_SIZE {
+                match self.tx.pop() {
+                    Some(byte) => iowrite8(MALTA_SERIAL_DATA, byte),
+                    None => break,
+                }
+            }
+        }
+        if self.tx.is_empty() {
+            self.set_ier(self.ier & !MALTA_SERIAL_IER_THRI);
+        } else {
+            self.set_ier(self.ier | MALTA_SERIAL_IER_THRI);
+        }
+    }
+
+    /// write out tx buffer by polling, used when interrupts are not available
+    pub fn flush(&mut self) {
+        while let Some(byte) = self.tx.pop() {
+            while !Self::thr_empty() {}
+            iowrite8(MALTA_SERIAL_DATA, byte);
+        }
+        self.set_ier(self.ier & !MALTA_SERIAL_IER_THRI);
+    }
+
+    /// get a byte from rx buffer, poll the uart if buffer is empty
+    pub fn getc(&mut self) -> Option<u8> {
+        self.receive();
+        self.rx.pop()
+    }
+
+    /// move received bytes from uart to rx buffer
+    fn receive(&mut self) -> bool {
+        let mut received = false;
+        while Self::data_ready() {
+            let byte = ioread8(MALTA_SERIAL_DATA);
+            let _ = self.rx.push(byte);
+            received = true;
+        }
+        received
+    }
+
+    /// interrupt handler, return if anything is received
+    pub fn intr(&mut self) -> bool {
+        let mut received = false;
+        while ioread8(MALTA_SERIAL_IIR) & MALTA_SERIAL_IIR_NO_INT == 0 {
+            received |= self.receive();
+            self.start_tx();
+        }
+        received
+    }
+}
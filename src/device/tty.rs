@@ -0,0 +1,207 @@
+use crate::{env::{group::env_group_notify, EnvID, Note}, err::Error, monitor::{monitor, monitor_key, MagicKey, MONITOR_KEY}, sync::{cell::UPSafeCell, wait_queue::WaitQueue}, util::ring_buffer::RingBuffer};
+
+use super::serial::{serial_getc, serial_putc};
+
+/// tty flag: line editing, input is delivered line by line
+pub const TTY_ICANON: usize = 0x1;
+/// tty flag: echo input characters
+pub const TTY_ECHO: usize = 0x2;
+/// tty flag: generate notifications for ^C and ^Z
+pub const TTY_ISIG: usize = 0x4;
+/// default tty flags
+pub const TTY_DEFAULT: usize = TTY_ICANON | TTY_ECHO | TTY_ISIG;
+
+/// size of cooked input buffer
+const TTY_BUF_SIZE: usize = 4096;
+/// max length of an edited line
+const TTY_LINE_MAX: usize = 1024;
+
+/// control characters
+const CTRL_C: u8 = 0x03;
+const CTRL_D: u8 = 0x04;
+const CTRL_U: u8 = 0x15;
+const CTRL_Z: u8 = 0x1a;
+const BACKSPACE: u8 = 0x08;
+const DELETE: u8 = 0x7f;
+
+/// tty ioctl commands
+#[repr(usize)]
+pub enum TtyCmd {
+    GetFlags = 0,
+    SetFlags = 1,
+    GetForeground = 2,
+    SetForeground = 3
+}
+
+/// global console tty
+pub static TTY: UPSafeCell<Tty> = UPSafeCell::new(Tty::new());
+
+/// feed characters received by serial device to tty, the monitor key sequence enters the monitor
+pub fn tty_intr() {
+    while let Some(c) = serial_getc() {
+        match monitor_key(c) {
+            MagicKey::Pass(c) => tty_input(c),
+            MagicKey::PassHeld(c) => {
+                tty_input(MONITOR_KEY);
+                tty_input(c);
+            }
+            MagicKey::Hold => {}
+            MagicKey::Enter => monitor("magic key", None, true)
+        }
+    }
+}
+
+/// feed a character to tty, notify foreground process group of ^C and ^Z
+fn tty_input(c: u8) {
+    let note = TTY.borrow_mut().input(c);
+    if let Some((pgid, note)) = note {
+        let _ = env_group_notify(pgid, note);
+    }
+}
+
+/// console tty with line discipline
+pub struct Tty {
+    flags: usize,
+    line: RingBuffer<u8, TTY_LINE_MAX>,
+    input: RingBuffer<u8, TTY_BUF_SIZE>,
+    eof: bool,
+    /// foreground process group
+    foreground: EnvID,
+    /// envs blocked on reading
+    pub readers: WaitQueue,
+}
+
+impl Tty {
+    /// create a new tty
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            flags: TTY_DEFAULT,
+            line: RingBuffer::new(0),
+            input: RingBuffer::new(0),
+            eof: false,
+            foreground: EnvID::new(0),
+            readers: WaitQueue::new()
+        }
+    }
+
+    /// echo a character if enabled
+    #[inline]
+    fn echo(&self, c: u8) {
+        if self.flags & TTY_ECHO != 0 {
+            serial_putc(c);
+        }
+    }
+
+    /// erase last character of edited line
+    fn erase(&mut self) -> bool {
+        if self.line.pop_back().is_some() {
+            self.echo(BACKSPACE);
+            self.echo(b' ');
+            self.echo(BACKSPACE);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// move edited line to input buffer
+    fn commit_line(&mut self) {
+        while let Some(c) = self.line.pop() {
+            let _ = self.input.push(c);
+        }
+        self.readers.wake_all();
+    }
+
+    /// process an input character, return the notification for foreground process group
+    pub fn input(&mut self, c: u8) -> Option<(EnvID, Note)> {
+        if self.flags & TTY_ISIG != 0 && (c == CTRL_C || c == CTRL_Z) {
+            self.echo(b'^');
+            self.echo(c + b'@');
+            self.echo(b'\n');
+            self.line.clear();
+            let note = if c == CTRL_C { Note::Interrupt } else { Note::Stop };
+            return if self.foreground.as_usize() != 0 { Some((self.foreground, note)) } else { None };
+        }
+        if self.flags & TTY_ICANON == 0 {
+            let _ = self.input.push(c);
+            self.echo(c);
+            self.readers.wake_all();
+            return None;
+        }
+        match c {
+            b'\r' | b'\n' => {
+                let _ = self.line.push(b'\n');
+                self.echo(b'\n');
+                self.commit_line();
+            },
+            BACKSPACE | DELETE => {
+                self.erase();
+            },
+            CTRL_U => {
+                while self.erase() {}
+            },
+            CTRL_D => {
+                if self.line.is_empty() {
+                    self.eof = true;
+                }
+                self.commit_line();
+            },
+            _ => {
+                if self.line.push(c).is_ok() {
+                    self.echo(c);
+                }
+            }
+        }
+        None
+    }
+
+    /// read cooked input, a canonical read stops at the end of a line.
+    /// return None if nothing can be read yet.
+    pub fn read(&mut self, buf: &mut [u8]) -> Option<usize> {
+        if self.input.is_empty() {
+            if self.eof {
+                self.eof = false;
+                return Some(0);
+            }
+            return None;
+        }
+        let mut n = 0;
+        while n < buf.len() {
+            match self.input.pop() {
+                Some(c) => {
+                    buf[n] = c;
+                    n += 1;
+                    if c == b'\n' && self.flags & TTY_ICANON != 0 {
+                        break;
+                    }
+                },
+                None => break
+            }
+        }
+        Some(n)
+    }
+
+    /// control tty mode and foreground process group
+    pub fn ioctl(&mut self, cmd: usize, arg: usize) -> Result<usize, Error> {
+        match cmd {
+            x if x == TtyCmd::GetFlags as usize => Ok(self.flags),
+            x if x == TtyCmd::SetFlags as usize => {
+                if arg & !TTY_DEFAULT != 0 {
+                    return Err(Error::Inval);
+                }
+                if arg & TTY_ICANON == 0 {
+                    self.commit_line();
+                }
+                self.flags = arg;
+                Ok(0)
+            },
+            x if x == TtyCmd::GetForeground as usize => Ok(self.foreground.as_usize()),
+            x if x == TtyCmd::SetForeground as usize => {
+                self.foreground = EnvID::new(arg);
+                Ok(0)
+            },
+            _ => Err(Error::Inval)
+        }
+    }
+}
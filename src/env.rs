@@ -6,29 +6,72 @@ pub mod schedule;
 pub mod syscall;
 /// semaphore
 pub mod sem;
+/// env table
+pub mod table;
+/// user visible env info
+pub mod info;
+/// resource accounting
+pub mod rusage;
+/// resource limits
+pub mod rlimit;
+/// out of memory killer
+pub mod oom;
+/// process groups and sessions
+pub mod group;
+/// env parent and child links
+pub mod tree;
+/// env debugging
+pub mod ptrace;
+/// core dumps of crashed envs
+pub mod coredump;
+/// syscall tracing
+pub mod strace;
+/// sampling profiler
+pub mod profile;
+/// frame ref count checker
+pub mod memcheck;
 
-use core::{fmt::{self, LowerHex}, mem::size_of, ptr::{addr_of, addr_of_mut, copy}};
+use core::{fmt::{self, Display, LowerHex}, mem::size_of, ptr::{addr_of, addr_of_mut, copy}, slice};
+
+use crate::{clock::{cycles_to_ticks, uptime_cycles, CLOCK}, debug, err::Error, exception::traps::{Trapframe, STATUS_EXL, STATUS_IE, STATUS_IM2, STATUS_IM7, STATUS_UM}, memory::{frame::{frame_alloc, frame_base_phy_addr, frame_base_size, frame_decref, frame_incref}, mmu::{PhysAddr, PhysPageNum, VirtAddr, KSTACKTOP, NASID, PDSHIFT, PGSHIFT, PTE_G, PTE_V, UENVS, UPAGES, USTACKTOP, UTOP, UVPT, UXSTACKTOP}, page_table::{PageTable, Pte, PAGE_TABLE_ENTRIES}, shm::SHMMNI, tlb::{tlb_flush, tlb_invalidate}}, sync::cell::UPSafeCell, util::{elf::{elf_from, elf_load_seg, Elf32Phdr, PT_LOAD}, queue::IndexLink}, warn};
 
 use alloc::vec::Vec;
 
-use crate::{err::Error, exception::traps::{Trapframe, STATUS_EXL, STATUS_IE, STATUS_IM7, STATUS_UM}, memory::{frame::{frame_alloc, frame_base_phy_addr, frame_base_size, frame_decref, frame_incref}, mmu::{PhysAddr, PhysPageNum, VirtAddr, KSTACKTOP, NASID, PDSHIFT, PGSHIFT, PTE_G, PTE_V, UENVS, UPAGES, USTACKTOP, UTOP, UVPT}, page_table::{PageTable, Pte, PAGE_TABLE_ENTRIES}, tlb::tlb_invalidate}, println, sync::cell::UPSafeCell, util::{elf::{elf_from, elf_load_seg, Elf32Phdr, PT_LOAD}, queue::IndexLink}};
+use self::{info::{EnvInfo, EnvStat, ENV_CMDLINE_MAX, ENV_NAME_LEN}, ptrace::StepBreak, rlimit::{Resource, Rlimit}, rusage::Rusage, table::{EnvTable, ENV_CHUNK}};
 
 /// log env size
 const LOG2NENV: usize = 10;
-/// env size
+/// max env size
 const NENV: usize = 1 << LOG2NENV;
 
+/// the table grows on demand only if a chunk is part of it, and chunks must fill it exactly
+const _: () = assert!(ENV_CHUNK < NENV && NENV % ENV_CHUNK == 0);
+
+/// exit code of env killed by a notification is this or'ed with the note
+pub const EXIT_NOTE: i32 = 0x80;
+/// exit code of env killed for exceeding its cpu limit
+pub const EXIT_CPU_LIMIT: i32 = 0x100;
+/// exit code of env killed by the oom killer
+pub const EXIT_OOM: i32 = 0x200;
+/// exit code of env killed by a fault is this or'ed with the signal, its core is kept
+pub const EXIT_CORE: i32 = 0x400;
+
 /// global env manager
 static ENV_MANAGER: UPSafeCell<EnvManager<'static>> = UPSafeCell::new(EnvManager::new());
 
 extern "C" {
     fn env_pop_tf(addr: usize, asid: usize) -> !;
+    fn env_idle() -> !;
 }
 
 /// init env manager
 #[inline]
 pub fn env_init() { ENV_MANAGER.borrow_mut().init(); }
 
+/// check if env manager is borrowed, the kernel monitor may have interrupted its user
+#[inline]
+pub fn env_busy() -> bool { ENV_MANAGER.try_borrow_mut().is_none() }
+
 /// get current env index
 #[inline]
 pub fn get_cur_env_ind() -> Option<usize> { ENV_MANAGER.borrow_mut().cur_env_ind }
@@ -76,6 +119,15 @@ pub enum EnvStatus {
     NotRunnable = 2
 }
 
+/// notifications sent to env
+#[repr(usize)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Note {
+    Interrupt = 1,
+    Stop = 2,
+    Continue = 3
+}
+
 /// env struct
 #[repr(C)]
 pub struct Env<'a> {
@@ -93,18 +145,47 @@ pub struct Env<'a> {
     env_ipc_perm: usize,
     env_user_tlb_mod_entry: usize,
     env_runs: usize,
+    env_notify_entry: usize,
+    env_notes: usize,
+    env_sleeping: bool,
+    env_sleep_seq: usize,
+    env_stopped: bool,
+    env_asid_gen: usize,
+    env_exit_code: i32,
+    env_name: [u8; ENV_NAME_LEN],
+    env_cmdline: Vec<u8>,
+    env_rusage: Rusage,
+    env_rusage_children: Rusage,
+    env_rlimit: Rlimit,
+    env_shm_attached: [u16; SHMMNI],
+    env_oom_protected: bool,
+    env_privileged: bool,
+    env_pgid: EnvID,
+    env_sid: EnvID,
+    env_first_child: Option<usize>,
+    env_prev_sibling: Option<usize>,
+    env_next_sibling: Option<usize>,
+    env_teardown: bool,
+    env_tracer: EnvID,
+    env_tracees: usize,
+    env_trace_stop: usize,
+    env_step_breaks: [Option<StepBreak>; 2],
+    env_strace: usize,
 }
 
 /// env manager struct
 pub struct EnvManager<'a> {
-    envs: Vec<Env<'a>>,
+    envs: EnvTable<'a>,
     base_pgdir: PageTable,
     env_free_list: IndexLink,
     env_sched_list: IndexLink,
     cur_env_ind: Option<usize>,
     asid_bitmap: [usize; NASID / 32],
+    asid_gen: usize,
     alloced_env: usize,
-    count: isize
+    count: isize,
+    mark: u64,
+    kernel_depth: usize
 }
 
 impl ASID {
@@ -124,7 +205,7 @@ impl ASID {
 
 impl EnvID {
     #[inline]
-    pub fn new(v: usize) -> Self {
+    pub const fn new(v: usize) -> Self {
         Self(v)
     }
     #[inline]
@@ -153,6 +234,24 @@ impl LowerHex for EnvID {
     }
 }
 
+impl<'a> Display for Env<'a> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{:x} {}", self.env_id, self.name())
+    }
+}
+
+impl Note {
+    /// convert int to note
+    pub fn from_usize(value: usize) -> Option<Self> {
+        match value {
+            x if x == Note::Interrupt as usize => Some(Note::Interrupt),
+            x if x == Note::Stop as usize => Some(Note::Stop),
+            x if x == Note::Continue as usize => Some(Note::Continue),
+            _ => None
+        }
+    }
+}
+
 impl From<usize> for EnvStatus {
     fn from(value: usize) -> Self {
         match value {
@@ -170,6 +269,7 @@ impl<'a> Env<'a> {
             env_tf: Trapframe::new(),
             env_id: EnvID::zero(),
             env_asid: ASID::zero(),
+            env_asid_gen: 0,
             env_parent_id: EnvID::zero(),
             env_status: EnvStatus::Free,
             env_pgdir: None,
@@ -180,13 +280,91 @@ impl<'a> Env<'a> {
             env_ipc_dstva: VirtAddr::zero(),
             env_ipc_perm: 0,
             env_user_tlb_mod_entry: 0,
-            env_runs: 0
+            env_runs: 0,
+            env_notify_entry: 0,
+            env_notes: 0,
+            env_sleeping: false,
+            env_sleep_seq: 0,
+            env_stopped: false,
+            env_exit_code: 0,
+            env_name: [0; ENV_NAME_LEN],
+            env_cmdline: Vec::new(),
+            env_rusage: Rusage::new(),
+            env_rusage_children: Rusage::new(),
+            env_rlimit: Rlimit::new(),
+            env_shm_attached: [0; SHMMNI],
+            env_oom_protected: false,
+            env_privileged: false,
+            env_pgid: EnvID::zero(),
+            env_sid: EnvID::zero(),
+            env_first_child: None,
+            env_prev_sibling: None,
+            env_next_sibling: None,
+            env_teardown: false,
+            env_tracer: EnvID::zero(),
+            env_tracees: 0,
+            env_trace_stop: 0,
+            env_step_breaks: [None; 2],
+            env_strace: 0
         }
     }
     /// load trap frame from pointer
     pub fn load_tf(&mut self, tf: *const Trapframe) {
         self.env_tf = unsafe {*tf};
     }
+    /// set name of env, truncated and nul terminated
+    pub fn set_name(&mut self, name: &[u8]) {
+        let n = name.len().min(ENV_NAME_LEN - 1);
+        self.env_name = [0; ENV_NAME_LEN];
+        self.env_name[..n].copy_from_slice(&name[..n]);
+    }
+    /// set command line of env, nul separated and truncated.
+    /// the name becomes the base name of the first argument without `.b`.
+    pub fn set_cmdline(&mut self, cmdline: &[u8]) {
+        let cmdline = &cmdline[..cmdline.len().min(ENV_CMDLINE_MAX)];
+        self.env_cmdline.clear();
+        self.env_cmdline.extend_from_slice(cmdline);
+        let arg0 = cmdline.split(|&c| c == 0).next().unwrap_or(&[]);
+        let base = arg0.rsplit(|&c| c == b'/').next().unwrap_or(arg0);
+        let base = base.strip_suffix(b".b").unwrap_or(base);
+        if !base.is_empty() {
+            self.set_name(base);
+            self.strace_boot();
+        }
+    }
+    /// name of env up to its nul
+    pub fn name(&self) -> &str {
+        let n = self.env_name.iter().position(|&c| c == 0).unwrap_or(ENV_NAME_LEN);
+        core::str::from_utf8(&self.env_name[..n]).unwrap_or("?")
+    }
+    /// number of distinct shared memory segments attached to env
+    pub fn shm_segments(&self) -> usize {
+        self.env_shm_attached.iter().filter(|&&n| n > 0).count()
+    }
+    /// divert env to its notify entry, saving trap frame on exception stack
+    fn deliver_note(&mut self) {
+        let note = self.env_notes.trailing_zeros() as usize;
+        self.env_notes &= !(1 << note);
+
+        let mut sp = VirtAddr::new(self.env_tf.regs[29]);
+        if sp < USTACKTOP || sp >= UXSTACKTOP {
+            sp = UXSTACKTOP;
+        }
+        let sp = VirtAddr::new(sp.as_usize() - size_of::<Trapframe>());
+        let tf = unsafe { slice::from_raw_parts(addr_of!(self.env_tf) as *const u8, size_of::<Trapframe>()) };
+        let saved = match &self.env_pgdir {
+            Some(pgdir) => pgdir.copy_out(sp, tf).is_ok(),
+            None => false
+        };
+        if !saved {
+            warn!("[{}] note {} dropped, exception stack not mapped", self, note);
+            return;
+        }
+        self.env_tf.regs[4] = note;
+        self.env_tf.regs[5] = sp.as_usize();
+        self.env_tf.regs[29] = sp.as_usize() - 8;
+        self.env_tf.cp0_epc = self.env_notify_entry;
+    }
 }
 
 impl<'a> EnvManager<'a> {
@@ -194,64 +372,101 @@ impl<'a> EnvManager<'a> {
     #[inline]
     pub const fn new() -> Self {
         EnvManager {
-            envs: Vec::new(),
+            envs: EnvTable::new(),
             base_pgdir: PageTable::new(),
             env_free_list: IndexLink::new(),
             env_sched_list: IndexLink::new(),
             cur_env_ind: None,
             asid_bitmap: [0; NASID / 32],
+            asid_gen: 1,
             alloced_env: 0,
-            count: 0
+            count: 0,
+            mark: 0,
+            kernel_depth: 0
         }
     }
 
     /// init a env mananger
     #[inline]
     pub fn init(&mut self) {
-        self.envs.resize_with(NENV, || {
-            Env::new()
-        });
-        self.env_free_list.init(NENV);
-        self.env_sched_list.init(NENV);
-        for i in (0..NENV).rev() {
-            self.envs[i].env_status = EnvStatus::Free;
-            self.env_free_list.insert_head(i);
-        }
+        self.env_free_list.init(0);
+        self.env_sched_list.init(0);
 
         self.base_pgdir.map_segment(ASID::zero(), frame_base_phy_addr(), UPAGES, frame_base_size(), PTE_G);
-        self.base_pgdir.map_segment(ASID::zero(), PhysAddr::from_kva(self.envs_raw_ptr()), UENVS, self.envs_size(), PTE_G);
+        self.grow().unwrap();
     }
 
-    /// get envs array pointer
-    #[inline]
-    fn envs_raw_ptr(&self) -> VirtAddr {
-        VirtAddr::from_ptr(self.envs.as_ptr())
+    /// add a chunk of free envs and map its info records at UENVS
+    fn grow(&mut self) -> Result<(), Error> {
+        let start = self.envs.len();
+        if start + ENV_CHUNK > NENV {
+            return Err(Error::NoFreeEnv);
+        }
+        let ppn = self.envs.grow()?;
+        let end = self.envs.len();
+        self.base_pgdir.insert(ASID::zero(), ppn, UENVS + start * size_of::<EnvInfo>(), PTE_G)?;
+        self.env_free_list.resize(end);
+        self.env_sched_list.resize(end);
+        for i in (start..end).rev() {
+            self.env_free_list.insert_head(i);
+        }
+        Ok(())
     }
-    
-    /// get envs array size
+
+    /// mark an asid as used in current generation
     #[inline]
-    fn envs_size(&self) -> usize {
-        self.envs.len() * size_of::<Env>()
+    fn asid_set(&mut self, i: usize) {
+        self.asid_bitmap[i >> 5] |= 1 << (i & 31);
     }
 
-    /// alloc a new asid
+    /// alloc a new asid in current generation.
+    /// when all asids are used, a new generation is started and tlb is flushed,
+    /// envs of old generations get new asids lazily in env_run.
     #[inline]
-    pub fn asid_alloc(&mut self) -> Result<ASID, Error> {
-        for i in 0..NASID {
-            let index = i >> 5;
-            let inner = i & 31;
-            if (self.asid_bitmap[index] & (1 << inner)) == 0 {
-                self.asid_bitmap[index] |= 1 << inner;
-                return Ok(ASID::new(i));
+    pub fn asid_alloc(&mut self) -> ASID {
+        loop {
+            for i in 0..NASID {
+                let index = i >> 5;
+                let inner = i & 31;
+                if (self.asid_bitmap[index] & (1 << inner)) == 0 {
+                    self.asid_bitmap[index] |= 1 << inner;
+                    return ASID::new(i);
+                }
             }
+            self.asid_new_generation();
         }
-        Err(Error::NoFreeEnv)
     }
 
-    /// free an asid
+    /// start a new asid generation, running env keeps its asid
+    fn asid_new_generation(&mut self) {
+        self.asid_gen += 1;
+        self.asid_bitmap = [0; NASID / 32];
+        tlb_flush();
+        if let Some(ind) = self.cur_env_ind {
+            let asid = self.envs[ind].env_asid;
+            self.asid_set(asid.as_usize());
+            self.envs[ind].env_asid_gen = self.asid_gen;
+        }
+    }
+
+    /// make sure env has an asid of current generation
+    #[inline]
+    fn asid_check(&mut self, ind: usize) {
+        if self.envs[ind].env_asid_gen != self.asid_gen {
+            let asid = self.asid_alloc();
+            let env = &mut self.envs[ind];
+            env.env_asid = asid;
+            env.env_asid_gen = self.asid_gen;
+        }
+    }
+
+    /// free an asid of env
     #[inline]
-    pub fn asid_free(&mut self, asid: ASID) {
-        let i = asid.as_usize();
+    pub fn asid_free(&mut self, ind: usize) {
+        if self.envs[ind].env_asid_gen != self.asid_gen {
+            return;
+        }
+        let i = self.envs[ind].env_asid.as_usize();
         let index = i >> 5;
         let inner = i & 31;
         self.asid_bitmap[index] &= !(1 << inner);
@@ -285,21 +500,48 @@ impl<'a> EnvManager<'a> {
     #[inline]
     pub fn alloc(&mut self, parent_id: EnvID) -> Result<EnvID, Error>{
         if self.env_free_list.is_empty() {
-            return Err(Error::NoFreeEnv);
+            self.grow()?;
         }
         let ind = self.env_free_list.first().unwrap();
         self.setup(ind)?;
         let envid = self.mkenvid(ind);
-        let asid = self.asid_alloc()?;
         let e = &mut self.envs[ind];
         e.env_user_tlb_mod_entry = 0;
         e.env_runs = 0;
+        e.env_notify_entry = 0;
+        e.env_notes = 0;
+        e.env_sleeping = false;
+        e.env_stopped = false;
+        e.env_exit_code = 0;
+        e.env_name = [0; ENV_NAME_LEN];
+        e.env_cmdline.clear();
+        e.env_rusage = Rusage::new();
+        e.env_rusage_children = Rusage::new();
+        e.env_rlimit = Rlimit::new();
+        e.env_shm_attached = [0; SHMMNI];
+        e.env_oom_protected = false;
+        e.env_privileged = false;
         e.env_id = envid;
-        e.env_asid = asid;
+        e.env_pgid = envid;
+        e.env_sid = envid;
+        e.env_first_child = None;
+        e.env_teardown = false;
+        e.env_tracer = EnvID::zero();
+        e.env_tracees = 0;
+        e.env_trace_stop = 0;
+        e.env_step_breaks = [None; 2];
+        e.env_strace = 0;
+        e.env_asid_gen = 0;
         e.env_parent_id = parent_id;
-        e.env_tf.cp0_status = STATUS_IM7 | STATUS_IE | STATUS_EXL | STATUS_UM;
+        e.env_tf.cp0_status = STATUS_IM7 | STATUS_IM2 | STATUS_IE | STATUS_EXL | STATUS_UM;
         e.env_tf.regs[29] = USTACKTOP.as_usize() - 4 - 4;
         self.env_free_list.remove(ind);
+        if parent_id.as_usize() != 0 {
+            if let Ok(parent) = self.envid2ind(parent_id, 0) {
+                self.link_child(parent, ind);
+            }
+        }
+        self.envs.sync_info(ind);
         Ok(envid)
     }
     /// convert env id to env index
@@ -310,12 +552,12 @@ impl<'a> EnvManager<'a> {
             return Ok(self.cur_env_ind.unwrap());
         }
         
-        let cur_env_id = self.envs[self.cur_env_ind.unwrap()].env_id;
         let e = &self.envs[id.envx()];
         if e.env_status == EnvStatus::Free || e.env_id != id {
             return Err(Error::BadEnv)
         }
         if checkperm != 0 {
+            let cur_env_id = self.envs[self.cur_env_ind.unwrap()].env_id;
             if e.env_id != cur_env_id && e.env_parent_id != cur_env_id {
                 return Err(Error::BadEnv);
             }
@@ -330,10 +572,23 @@ impl<'a> EnvManager<'a> {
     /// free an env identified by index
     #[inline]
     pub fn free(&mut self, ind: usize) {
-        println!("[{:x}] free env [{:x}]", match self.cur_env_ind {
-            Some(ind) => self.get_env(ind).env_id.0,
-            None => EnvID::zero().0
-        }, self.get_env(ind).env_id.0);
+        match self.cur_env_ind {
+            Some(cur) => debug!("[{}] free env [{}]", self.envs[cur], self.envs[ind]),
+            None => debug!("[{:x}] free env [{}]", EnvID::zero(), self.envs[ind])
+        }
+
+        self.sample_rss(ind);
+        let mut total = self.envs[ind].env_rusage;
+        total.add(&self.envs[ind].env_rusage_children);
+        let parent_id = self.envs[ind].env_parent_id;
+        if parent_id.as_usize() != 0 {
+            if let Ok(parent) = self.envid2ind(parent_id, 0) {
+                self.envs[parent].env_rusage_children.add(&total);
+                self.unlink_child(parent, ind);
+            }
+        }
+        self.orphan_children(ind);
+        self.trace_release(ind);
 
         let env = &mut self.envs[ind];
         if let Some(pgdir) = &mut env.env_pgdir {
@@ -357,23 +612,128 @@ impl<'a> EnvManager<'a> {
         }
         let asid = env.env_asid;
         env.env_status = EnvStatus::Free;
-        self.asid_free(asid);
+        self.asid_free(ind);
         tlb_invalidate(asid, UVPT + (UVPT.pdx() << PGSHIFT));
         self.env_free_list.insert_head(ind);
         self.env_sched_list.remove(ind);
+        self.envs.sync_info(ind);
+        if memcheck::memcheck_on_free() {
+            self.memcheck();
+        }
+    }
+
+    /// mark an env not runnable and remove it from sched list
+    #[inline]
+    pub fn block(&mut self, ind: usize) {
+        if self.envs[ind].env_status == EnvStatus::Runnable {
+            self.envs[ind].env_status = EnvStatus::NotRunnable;
+            self.envs.sync_info(ind);
+            self.env_sched_list.remove(ind);
+        }
+    }
+
+    /// mark a blocked env runnable and insert it into sched list.
+    /// an env stopped by a note or its tracer stays blocked until it is resumed.
+    #[inline]
+    pub fn unblock(&mut self, ind: usize) {
+        if self.envs[ind].env_stopped || self.envs[ind].env_trace_stop != 0 {
+            return;
+        }
+        self.envs[ind].env_sleeping = false;
+        if self.envs[ind].env_status == EnvStatus::NotRunnable {
+            self.envs[ind].env_status = EnvStatus::Runnable;
+            self.envs.sync_info(ind);
+            self.env_sched_list.insert_tail(ind);
+        }
+    }
+
+    /// let an env run after a stop. a sleeping env restarts its syscall as its wakeup may have
+    /// come while it was stopped, an env blocked in ipc_recv stays blocked until a send.
+    pub fn resume(&mut self, ind: usize) {
+        let env = &self.envs[ind];
+        if env.env_sleeping || env.env_ipc_receiving == 0 {
+            self.unblock(ind);
+        }
+    }
+
+    /// charge cycles since last mark to current env
+    #[inline]
+    fn charge(&mut self, kernel: bool) {
+        let now = uptime_cycles();
+        let elapsed = now - self.mark;
+        self.mark = now;
+        if let Some(ind) = self.cur_env_ind {
+            let ru = &mut self.envs[ind].env_rusage;
+            if kernel {
+                ru.stime += elapsed;
+            } else {
+                ru.utime += elapsed;
+            }
+        }
+    }
+
+    /// charge kernel time of current env before it is switched out
+    #[inline]
+    fn switch_out(&mut self) {
+        self.charge(true);
+        self.kernel_depth = 0;
+        if let Some(ind) = self.cur_env_ind {
+            self.sample_rss(ind);
+        }
+    }
+
+    /// update peak resident pages of env
+    #[inline]
+    fn sample_rss(&mut self, ind: usize) {
+        let env = &mut self.envs[ind];
+        let rss = env.env_pgdir.as_ref().map_or(0, |pgdir| pgdir.resident());
+        env.env_rusage.maxrss = env.env_rusage.maxrss.max(rss);
+    }
+
+    /// check that env may map one more page at va, remapping a mapped va is always allowed
+    pub fn check_frames(&self, ind: usize, va: VirtAddr) -> Result<(), Error> {
+        let env = &self.envs[ind];
+        if !env.env_rlimit.is_limited(Resource::Frames) {
+            return Ok(());
+        }
+        match &env.env_pgdir {
+            Some(pgdir) if pgdir.translate(va).is_none() => env.env_rlimit.check(Resource::Frames, pgdir.resident(), Error::NoMem),
+            _ => Ok(())
+        }
+    }
 
+    /// check that env may create one more child
+    pub fn check_children(&self, ind: usize) -> Result<(), Error> {
+        let env = &self.envs[ind];
+        if !env.env_rlimit.is_limited(Resource::Children) {
+            return Ok(());
+        }
+        env.env_rlimit.check(Resource::Children, self.children(ind).len(), Error::NoFreeEnv)
+    }
+
+    /// whether env has used up its cpu limit
+    fn cpu_exceeded(&self, ind: usize) -> bool {
+        let env = &self.envs[ind];
+        if !env.env_rlimit.is_limited(Resource::CpuTicks) {
+            return false;
+        }
+        let ru = &env.env_rusage;
+        cycles_to_ticks(ru.utime + ru.stime) >= env.env_rlimit.get(Resource::CpuTicks) as u64
     }
 
     /// create a env from code with priority
     #[inline]
-    pub fn create(&mut self, binary: &[u8], size: usize, priority: usize) -> EnvID {
+    pub fn create(&mut self, name: &str, binary: &[u8], size: usize, priority: usize) -> EnvID {
         let envid = self.alloc(EnvID::zero()).unwrap();
         let ind = envid.envx();
         let env: &mut Env<'a> = &mut self.envs[ind];
+        env.set_cmdline(name.as_bytes());
         env.env_pri = priority;
+        env.env_privileged = true;
         env.env_status = EnvStatus::Runnable;
         load_icode(env, binary, size);
         self.env_sched_list.insert_head(ind);
+        self.envs.sync_info(ind);
         envid
     }
 }
@@ -381,17 +741,44 @@ impl<'a> EnvManager<'a> {
 /// destroy an env
 #[inline]
 pub fn env_destroy(ind: usize) {
+    let tree = ENV_MANAGER.borrow_mut().envs[ind].env_teardown;
+    env_destroy_tree(ind, tree);
+}
+
+/// destroy an env, and all its descendants if tree is set
+pub fn env_destroy_tree(ind: usize, tree: bool) {
     let mut em = ENV_MANAGER.borrow_mut();
+    if tree {
+        for desc in em.descendants(ind) {
+            em.free(desc);
+        }
+    }
     em.free(ind);
-    let cur_env_ind = em.cur_env_ind.unwrap();
-    if ind == cur_env_ind {
+    let cur = em.cur_env_ind;
+    if cur.is_some_and(|cur| em.envs[cur].env_status == EnvStatus::Free) {
         em.cur_env_ind = None;
-        println!("I am killed ...");
+        debug!("I am killed ...");
         drop(em);
         env_sched(1);
     }
 }
 
+/// destroy current env if it has used up its cpu limit
+pub fn env_check_cpu_limit() {
+    let mut em = ENV_MANAGER.borrow_mut();
+    let ind = match em.cur_env_ind {
+        Some(ind) => ind,
+        None => return
+    };
+    em.charge(true);
+    if em.cpu_exceeded(ind) {
+        warn!("[{}] cpu limit exceeded", em.envs[ind]);
+        em.envs[ind].env_exit_code = EXIT_CPU_LIMIT;
+        drop(em);
+        env_destroy(ind);
+    }
+}
+
 /// code befroe a env start running
 pub fn pre_env_run(_: usize) {
 
@@ -404,6 +791,7 @@ pub fn env_run(ind: usize) -> ! {
     let env = em.get_env(ind);
     assert!(env.env_status == EnvStatus::Runnable);
     
+    em.switch_out();
     let cur = em.cur_env_ind;
     if let Some(cur_ind) = cur {
         let kstacktop = (KSTACKTOP - size_of::<Trapframe>()) as *mut Trapframe;
@@ -411,14 +799,20 @@ pub fn env_run(ind: usize) -> ! {
         
     }
 
+    em.asid_check(ind);
     em.cur_env_ind = Some(ind);
+    em.envs[ind].env_runs += 1;
+    em.envs.sync_info(ind);
     let curenv = em.get_env(ind);
-    curenv.env_runs += 1;
+    if curenv.env_notes != 0 && curenv.env_notify_entry != 0 {
+        curenv.deliver_note();
+    }
     
     //println!("{:x} from {:x}", curenv.env_id.0, curenv.env_tf.cp0_epc);
     let tf_addr = addr_of!(curenv.env_tf) as usize;
     let asid = curenv.env_asid.as_usize();
     drop(em);
+    CLOCK.borrow_mut().arm_tick();
     unsafe {env_pop_tf(tf_addr, asid)}
 
 }
@@ -431,7 +825,15 @@ pub fn env_sched(y: i32) -> ! {
     let next_run;
     if y != 0 || em.count == 0 || e.is_none() || em.get_env(e.unwrap()).env_status != EnvStatus::Runnable {
         if em.env_sched_list.is_empty() {
-            panic!("Sched list empty");
+            if let Some(ind) = e {
+                let kstacktop = (KSTACKTOP - size_of::<Trapframe>()) as *mut Trapframe;
+                em.get_env(ind).load_tf(kstacktop);
+            }
+            em.switch_out();
+            em.cur_env_ind = None;
+            drop(em);
+            CLOCK.borrow_mut().arm_idle();
+            unsafe { env_idle() }
         }
         if e.is_some() && em.get_env(e.unwrap()).env_status == EnvStatus::Runnable {
             em.env_sched_list.remove(e.unwrap());
@@ -446,6 +848,82 @@ pub fn env_sched(y: i32) -> ! {
     env_run(next_run);
 }
 
+/// leave the idle loop if an irq made an env runnable, the idle timer may be far away
+pub fn env_leave_idle() {
+    let em = ENV_MANAGER.borrow_mut();
+    if em.cur_env_ind.is_none() && !em.env_sched_list.is_empty() {
+        drop(em);
+        env_sched(1);
+    }
+}
+
+/// block current env in a syscall, the syscall is restarted when it is woken up.
+/// return the sequence number of this sleep, a wakeup for an earlier one is ignored.
+pub fn env_sleep() -> usize {
+    let mut em = ENV_MANAGER.borrow_mut();
+    let ind = em.cur_env_ind.unwrap();
+    em.block(ind);
+    let env = &mut em.envs[ind];
+    env.env_sleeping = true;
+    env.env_sleep_seq = env.env_sleep_seq.wrapping_add(1);
+    let tf = (KSTACKTOP - size_of::<Trapframe>()) as *mut Trapframe;
+    unsafe { (*tf).cp0_epc -= 4; }
+    env.env_sleep_seq
+}
+
+/// check if an env is still in the sleep numbered seq, not woken by a note or destroyed since
+pub fn env_sleeping(envid: EnvID, seq: usize) -> bool {
+    let em = ENV_MANAGER.borrow_mut();
+    let env = &em.envs[envid.envx()];
+    env.env_status != EnvStatus::Free && env.env_id == envid && env.env_sleeping && env.env_sleep_seq == seq
+}
+
+/// wake up an env blocked by the env_sleep numbered seq
+pub fn env_wakeup(envid: EnvID, seq: usize) {
+    if env_sleeping(envid, seq) {
+        ENV_MANAGER.borrow_mut().unblock(envid.envx());
+    }
+}
+
+/// send a notification to an env.
+/// without a notify entry, interrupt destroys the env, stop holds it stopped and continue
+/// resumes a stopped env.
+pub fn env_notify(ind: usize, note: Note) {
+    let mut em = ENV_MANAGER.borrow_mut();
+    let env = &mut em.envs[ind];
+    if env.env_notify_entry != 0 {
+        env.env_notes |= 1 << note as usize;
+        if env.env_sleeping {
+            em.unblock(ind);
+        }
+        return;
+    }
+    match note {
+        Note::Interrupt => {
+            env.env_exit_code = EXIT_NOTE | note as i32;
+            drop(em);
+            env_destroy(ind);
+        },
+        Note::Stop => {
+            env.env_stopped = true;
+            em.block(ind);
+            if em.cur_env_ind == Some(ind) {
+                drop(em);
+                // env_sched does not return, so the syscall result must be set here
+                let tf = (KSTACKTOP - size_of::<Trapframe>()) as *mut Trapframe;
+                unsafe { (*tf).regs[2] = 0; }
+                env_sched(1);
+            }
+        },
+        Note::Continue => {
+            if env.env_stopped {
+                env.env_stopped = false;
+                em.resume(ind);
+            }
+        }
+    }
+}
+
 /// map elf data to memeory
 fn load_icode_mapper(env: &mut Env, va: VirtAddr, offset: usize, perm: usize, src: Option<&[u8]>, len: usize) -> Result<(), Error> {
     let ppn = frame_alloc()?;
@@ -494,8 +972,47 @@ pub fn env_free(ind: usize) {
     ENV_MANAGER.borrow_mut().free(ind);
 }
 /// create an env
-pub fn env_create(binary: &[u8], size: usize, priority: usize) -> EnvID {
-    ENV_MANAGER.borrow_mut().create(binary, size, priority)
+pub fn env_create(name: &str, binary: &[u8], size: usize, priority: usize) -> EnvID {
+    ENV_MANAGER.borrow_mut().create(name, binary, size, priority)
+}
+
+/// account exception entry, time since last return to user is user time
+pub fn env_enter_kernel() {
+    let mut em = ENV_MANAGER.borrow_mut();
+    if em.kernel_depth == 0 {
+        em.charge(false);
+    }
+    em.kernel_depth += 1;
+}
+
+/// account exception return, time since entry is kernel time
+pub fn env_leave_kernel() {
+    let mut em = ENV_MANAGER.borrow_mut();
+    em.kernel_depth = em.kernel_depth.saturating_sub(1);
+    if em.kernel_depth == 0 {
+        em.charge(true);
+    }
+}
+
+/// update resource usage of current env
+pub fn cur_rusage<F>(f: F)
+where
+    F : FnOnce(&mut Rusage) {
+    let mut em = ENV_MANAGER.borrow_mut();
+    if let Some(ind) = em.cur_env_ind {
+        f(&mut em.envs[ind].env_rusage);
+    }
+}
+
+/// check that current env may map one more page at va
+pub fn cur_check_frames(va: VirtAddr) -> Result<(), Error> {
+    let em = ENV_MANAGER.borrow_mut();
+    let ind = em.cur_env_ind.unwrap();
+    let result = em.check_frames(ind, va);
+    if result.is_err() {
+        warn!("[{}] frame limit exceeded at {:x}", em.envs[ind], va.as_usize());
+    }
+    result
 }
 
 /// operate on page dir of current env
@@ -515,11 +1032,51 @@ where
         f(pgdir);
     }
 }
+/// ids of live envs
+pub fn env_list() -> Vec<EnvID> {
+    let em = ENV_MANAGER.borrow_mut();
+    (0..em.envs.len())
+        .filter(|&ind| em.envs[ind].env_status != EnvStatus::Free)
+        .map(|ind| em.envs[ind].env_id)
+        .collect()
+}
+/// snapshot of an env
+pub fn env_stat(envid: EnvID) -> Result<EnvStat, Error> {
+    let mut em = ENV_MANAGER.borrow_mut();
+    let ind = em.envid2ind(envid, 0)?;
+    em.charge(true);
+    Ok(EnvStat::from(&em.envs[ind]))
+}
+/// read memory of an env through its page table
+pub fn env_read(envid: EnvID, va: VirtAddr, len: usize) -> Result<Vec<u8>, Error> {
+    let em = ENV_MANAGER.borrow_mut();
+    let ind = em.envid2ind(envid, 0)?;
+    em.trace_read(ind, va, len)
+}
+/// write memory of an env through its page table
+pub fn env_write(envid: EnvID, va: VirtAddr, data: &[u8]) -> Result<(), Error> {
+    let mut em = ENV_MANAGER.borrow_mut();
+    let ind = em.envid2ind(envid, 0)?;
+    em.trace_write(ind, va, data)
+}
+/// operate on trap frame of an env, current env uses the frame saved on kernel stack
+pub fn env_trapframe<F, R>(envid: EnvID, f: F) -> Result<R, Error>
+where
+    F : FnOnce(&mut Trapframe) -> R {
+    let mut em = ENV_MANAGER.borrow_mut();
+    let ind = em.envid2ind(envid, 0)?;
+    if em.cur_env_ind == Some(ind) {
+        let tf = (KSTACKTOP - size_of::<Trapframe>()) as *mut Trapframe;
+        Ok(f(unsafe { &mut *tf }))
+    } else {
+        Ok(f(&mut em.envs[ind].env_tf))
+    }
+}
 
 /// create env from code macro
 #[macro_export]
 macro_rules! env_create_pri {
     ($name: ident, $pri: expr) => {
-        crate::env::env_create(&concat_idents!(BINARY_, $name, _START), concat_idents!(BINARY_, $name, _SIZE), $pri)
+        crate::env::env_create(stringify!($name), &concat_idents!(BINARY_, $name, _START), concat_idents!(BINARY_, $name, _SIZE), $pri)
     };
 }
\ No newline at end of file
@@ -0,0 +1,295 @@
+use alloc::vec::Vec;
+use core::{mem::size_of, slice};
+
+use crate::{err::Error, exception::traps::Trapframe, memory::{frame::{frame_decref, frame_incref}, mmu::{PhysPageNum, VirtAddr, KSTACKTOP, PAGE_SIZE, PTE_D, UTOP}}, sync::{cell::UPSafeCell, wait_queue::WaitQueue}, util::elf::{Elf32Ehdr, Elf32Nhdr, Elf32Phdr, NT_PRPSINFO, NT_PRSTATUS, PF_R, PF_W, PF_X, PT_LOAD, PT_NOTE}, warn};
+
+use super::{env_destroy, EnvID, EnvManager, ENV_MANAGER, EXIT_CORE};
+
+/// most bytes moved by one sys_core_read
+pub const CORE_XFER_MAX: usize = PAGE_SIZE;
+
+/// owner name of the notes, padded to 4 bytes
+const NOTE_NAME: &[u8; 8] = b"CORE\0\0\0\0";
+/// general registers in a mips o32 register set
+const ELF_NGREG: usize = 45;
+/// index of r0 in a mips o32 register set, the first words are padding
+const EF_REG0: usize = 6;
+
+/// latest core dump, a new crash replaces it
+pub static CORE: UPSafeCell<Option<CoreDump>> = UPSafeCell::new(None);
+/// env that may read and drop every core dump, set by sys_core_wait
+static CORE_SUPERVISOR: UPSafeCell<EnvID> = UPSafeCell::new(EnvID::new(0));
+/// core supervisor waiting for a crash
+pub static CORE_WAITERS: UPSafeCell<WaitQueue> = UPSafeCell::new(WaitQueue::new());
+
+/// `elf_prstatus` of mips o32
+#[repr(C)]
+struct PrStatus {
+    si_signo: i32,
+    si_code: i32,
+    si_errno: i32,
+    pr_cursig: i16,
+    pr_pad: i16,
+    pr_sigpend: u32,
+    pr_sighold: u32,
+    pr_pid: u32,
+    pr_ppid: u32,
+    pr_pgrp: u32,
+    pr_sid: u32,
+    pr_times: [u32; 8],
+    pr_reg: [u32; ELF_NGREG],
+    pr_fpvalid: i32
+}
+
+/// `elf_prpsinfo` of mips o32
+#[repr(C)]
+struct PrPsInfo {
+    pr_state: u8,
+    pr_sname: u8,
+    pr_zomb: u8,
+    pr_nice: i8,
+    pr_flag: u32,
+    pr_uid: u32,
+    pr_gid: u32,
+    pr_pid: u32,
+    pr_ppid: u32,
+    pr_pgrp: u32,
+    pr_sid: u32,
+    pr_fname: [u8; 16],
+    pr_psargs: [u8; 80]
+}
+
+/// core dump info returned by sys_core_info
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct CoreInfo {
+    pub core_envid: u32,
+    pub core_signal: u32,
+    pub core_size: u32
+}
+
+/// ELF core file of a crashed env.
+/// the headers and notes are built at crash time, page contents stay in the env's frames,
+/// which are held until the dump is dropped.
+pub struct CoreDump {
+    envid: EnvID,
+    parent: EnvID,
+    signal: usize,
+    header: Vec<u8>,
+    pages: Vec<PhysPageNum>
+}
+
+/// raw bytes of a plain struct
+fn as_bytes<T>(v: &T) -> &[u8] {
+    unsafe { slice::from_raw_parts(v as *const T as *const u8, size_of::<T>()) }
+}
+
+/// append a note named CORE
+fn push_note<T>(buf: &mut Vec<u8>, n_type: u32, desc: &T) {
+    buf.extend_from_slice(as_bytes(&Elf32Nhdr::new(5, size_of::<T>(), n_type)));
+    buf.extend_from_slice(NOTE_NAME);
+    buf.extend_from_slice(as_bytes(desc));
+}
+
+/// copy bytes into a fixed field, truncated and nul padded
+fn fill(dst: &mut [u8], src: &[u8]) {
+    let n = src.len().min(dst.len() - 1);
+    dst[..n].copy_from_slice(&src[..n]);
+}
+
+/// segment flags of a mapping
+fn seg_flags(perm: usize) -> u32 {
+    if perm & PTE_D != 0 { PF_R | PF_W | PF_X } else { PF_R | PF_X }
+}
+
+impl CoreDump {
+    /// size of the core file in bytes
+    pub fn size(&self) -> usize {
+        self.header.len() + self.pages.len() * PAGE_SIZE
+    }
+
+    /// frames of the dumped pages, a ref is held on each
+    pub fn pages(&self) -> &[PhysPageNum] {
+        &self.pages
+    }
+
+    /// info of the core file
+    pub fn info(&self) -> CoreInfo {
+        CoreInfo {
+            core_envid: self.envid.as_usize() as u32,
+            core_signal: self.signal as u32,
+            core_size: self.size() as u32
+        }
+    }
+
+    /// read the core file at offset, return number of bytes read
+    pub fn read(&self, offset: usize, buf: &mut [u8]) -> usize {
+        let mut done = 0;
+        while done < buf.len() && offset + done < self.size() {
+            let pos = offset + done;
+            let src = if pos < self.header.len() {
+                &self.header[pos..]
+            } else {
+                let page = pos - self.header.len();
+                let kva = self.pages[page / PAGE_SIZE].into_kva() + page % PAGE_SIZE;
+                unsafe { slice::from_raw_parts(kva.as_ptr::<u8>(), PAGE_SIZE - page % PAGE_SIZE) }
+            };
+            let n = src.len().min(buf.len() - done);
+            buf[done..done + n].copy_from_slice(&src[..n]);
+            done += n;
+        }
+        done
+    }
+}
+
+impl Drop for CoreDump {
+    fn drop(&mut self) {
+        for ppn in self.pages.iter() {
+            frame_decref(*ppn);
+        }
+    }
+}
+
+impl<'a> EnvManager<'a> {
+    /// notes of a crashed env: registers and command line
+    fn core_notes(&self, ind: usize, tf: &Trapframe, signal: usize) -> Vec<u8> {
+        let env = &self.envs[ind];
+        let mut reg = [0; ELF_NGREG];
+        for (i, r) in tf.regs.iter().enumerate() {
+            reg[EF_REG0 + i] = *r as u32;
+        }
+        let tail = [tf.lo, tf.hi, tf.cp0_epc, tf.cp0_badvaddr, tf.cp0_status, tf.cp0_cause];
+        for (i, r) in tail.iter().enumerate() {
+            reg[EF_REG0 + 32 + i] = *r as u32;
+        }
+        let status = PrStatus {
+            si_signo: signal as i32,
+            si_code: 0,
+            si_errno: 0,
+            pr_cursig: signal as i16,
+            pr_pad: 0,
+            pr_sigpend: 0,
+            pr_sighold: 0,
+            pr_pid: env.env_id.as_usize() as u32,
+            pr_ppid: env.env_parent_id.as_usize() as u32,
+            pr_pgrp: env.env_pgid.as_usize() as u32,
+            pr_sid: env.env_sid.as_usize() as u32,
+            pr_times: [0; 8],
+            pr_reg: reg,
+            pr_fpvalid: 0
+        };
+        let mut info = PrPsInfo {
+            pr_state: 0,
+            pr_sname: b'R',
+            pr_zomb: 0,
+            pr_nice: 0,
+            pr_flag: 0,
+            pr_uid: 0,
+            pr_gid: 0,
+            pr_pid: status.pr_pid,
+            pr_ppid: status.pr_ppid,
+            pr_pgrp: status.pr_pgrp,
+            pr_sid: status.pr_sid,
+            pr_fname: [0; 16],
+            pr_psargs: [0; 80]
+        };
+        fill(&mut info.pr_fname, env.name().as_bytes());
+        let cmdline = env.env_cmdline.strip_suffix(&[0]).unwrap_or(&env.env_cmdline);
+        let args: Vec<u8> = cmdline.iter().map(|&c| if c == 0 { b' ' } else { c }).collect();
+        fill(&mut info.pr_psargs, &args);
+        let mut notes = Vec::new();
+        push_note(&mut notes, NT_PRSTATUS, &status);
+        push_note(&mut notes, NT_PRPSINFO, &info);
+        notes
+    }
+
+    /// dump an env with the registers in tf.
+    /// every mapped user page becomes part of a PT_LOAD segment, adjacent pages with the same
+    /// permission share one.
+    pub fn core_dump(&self, ind: usize, tf: &Trapframe, signal: usize) -> CoreDump {
+        let mapped = match &self.envs[ind].env_pgdir {
+            Some(pgdir) => pgdir.mapped(UTOP),
+            None => Vec::new()
+        };
+        let mut runs: Vec<(VirtAddr, usize, u32)> = Vec::new();
+        for (va, pte) in mapped.iter() {
+            let flags = seg_flags(pte.perm());
+            match runs.last_mut() {
+                Some((start, n, f)) if *f == flags && *start + *n * PAGE_SIZE == *va => *n += 1,
+                _ => runs.push((*va, 1, flags))
+            }
+        }
+        let notes = self.core_notes(ind, tf, signal);
+        let phnum = runs.len() + 1;
+        let notes_off = size_of::<Elf32Ehdr>() + phnum * size_of::<Elf32Phdr>();
+        let data_off = (notes_off + notes.len()).next_multiple_of(PAGE_SIZE);
+
+        let mut header = Vec::with_capacity(data_off);
+        header.extend_from_slice(as_bytes(&Elf32Ehdr::new_core(phnum)));
+        header.extend_from_slice(as_bytes(&Elf32Phdr::new(PT_NOTE, notes_off, 0, notes.len(), 0, 0)));
+        let mut offset = data_off;
+        for (va, n, flags) in runs.iter() {
+            let size = n * PAGE_SIZE;
+            header.extend_from_slice(as_bytes(&Elf32Phdr::new(PT_LOAD, offset, va.as_usize(), size, size, *flags)));
+            offset += size;
+        }
+        header.extend_from_slice(&notes);
+        header.resize(data_off, 0);
+
+        let pages = mapped.iter().map(|(_, pte)| {
+            frame_incref(pte.ppn());
+            pte.ppn()
+        }).collect();
+        CoreDump {
+            envid: self.envs[ind].env_id,
+            parent: self.envs[ind].env_parent_id,
+            signal,
+            header,
+            pages
+        }
+    }
+
+    /// make current env the core supervisor, unless another live env is
+    pub fn core_supervise(&self) -> Result<(), Error> {
+        let cur = self.envs[self.cur_env_ind.unwrap()].env_id;
+        let mut supervisor = CORE_SUPERVISOR.borrow_mut();
+        if supervisor.as_usize() != 0 && *supervisor != cur && self.envid2ind(*supervisor, 0).is_ok() {
+            return Err(Error::BadEnv);
+        }
+        *supervisor = cur;
+        Ok(())
+    }
+
+    /// check that current env may read or drop a core dump,
+    /// it must be the parent of the crashed env or the core supervisor
+    pub fn core_permitted(&self, core: &CoreDump) -> Result<(), Error> {
+        let cur = self.envs[self.cur_env_ind.unwrap()].env_id;
+        if cur != core.parent && cur != *CORE_SUPERVISOR.borrow_mut() {
+            return Err(Error::BadEnv);
+        }
+        Ok(())
+    }
+}
+
+/// drop the held core dump, return if there was one
+pub fn core_release() -> bool {
+    let core = CORE.borrow_mut().take();
+    core.is_some()
+}
+
+/// dump current env to the core buffer and destroy it.
+/// the registers are those saved on kernel stack when the fault was taken.
+pub fn env_crash(signal: usize) {
+    let tf = unsafe { &*((KSTACKTOP - size_of::<Trapframe>()) as *const Trapframe) };
+    let mut em = ENV_MANAGER.borrow_mut();
+    let ind = em.cur_env_ind.unwrap();
+    let core = em.core_dump(ind, tf, signal);
+    let env = &mut em.envs[ind];
+    warn!("[{}] crashed with signal {} at {:x}, {} bytes of core", env, signal, tf.cp0_epc, core.size());
+    env.env_exit_code = EXIT_CORE | signal as i32;
+    drop(em);
+    let old = CORE.borrow_mut().replace(core);
+    drop(old);
+    CORE_WAITERS.borrow_mut().wake_all();
+    env_destroy(ind);
+}
@@ -0,0 +1,108 @@
+use alloc::vec::Vec;
+
+use crate::err::Error;
+
+use super::{env_destroy, env_notify, EnvID, EnvManager, EnvStatus, Note, ENV_MANAGER};
+
+impl<'a> EnvManager<'a> {
+    /// live envs of a process group, the current env comes last
+    fn group_members(&self, pgid: EnvID) -> Vec<EnvID> {
+        let mut members = Vec::new();
+        let mut cur = None;
+        for ind in 0..self.envs.len() {
+            let env = &self.envs[ind];
+            if env.env_status == EnvStatus::Free || env.env_pgid != pgid {
+                continue;
+            }
+            if self.cur_env_ind == Some(ind) {
+                cur = Some(env.env_id);
+            } else {
+                members.push(env.env_id);
+            }
+        }
+        members.extend(cur);
+        members
+    }
+
+    /// whether any live env of the session is in the process group
+    fn group_exists(&self, pgid: EnvID, sid: EnvID) -> bool {
+        (0..self.envs.len()).any(|ind| {
+            let env = &self.envs[ind];
+            env.env_status != EnvStatus::Free && env.env_pgid == pgid && env.env_sid == sid
+        })
+    }
+
+    /// check that current env may notify or destroy a process group,
+    /// it must be in the session of the group or the parent of the group leader
+    pub fn check_group(&self, pgid: EnvID) -> Result<(), Error> {
+        let cur = &self.envs[self.cur_env_ind.unwrap()];
+        let members = self.group_members(pgid);
+        if members.is_empty() {
+            return Err(Error::BadEnv);
+        }
+        let same_session = members.iter().all(|id| self.envs[id.envx()].env_sid == cur.env_sid);
+        let leader_parent = self.envid2ind(pgid, 0).map_or(false, |leader| self.envs[leader].env_parent_id == cur.env_id);
+        if !same_session && !leader_parent {
+            return Err(Error::BadEnv);
+        }
+        Ok(())
+    }
+
+    /// move env to a process group of its session, pgid zero means the env's own id.
+    /// a session leader can not leave its group.
+    pub fn set_pgid(&mut self, ind: usize, pgid: EnvID) -> Result<(), Error> {
+        let env = &self.envs[ind];
+        let pgid = if pgid.as_usize() == 0 { env.env_id } else { pgid };
+        if env.env_sid == env.env_id {
+            return Err(Error::Inval);
+        }
+        if pgid != env.env_id && !self.group_exists(pgid, env.env_sid) {
+            return Err(Error::BadEnv);
+        }
+        self.envs[ind].env_pgid = pgid;
+        Ok(())
+    }
+
+    /// make env leader of a new session and process group, return the session id.
+    /// a process group leader can not start a session.
+    pub fn set_sid(&mut self, ind: usize) -> Result<EnvID, Error> {
+        let id = self.envs[ind].env_id;
+        if self.envs[ind].env_pgid == id || !self.group_members(id).is_empty() {
+            return Err(Error::Inval);
+        }
+        let env = &mut self.envs[ind];
+        env.env_sid = id;
+        env.env_pgid = id;
+        Ok(id)
+    }
+}
+
+/// send a notification to every env of a process group, callers check permission
+pub fn env_group_notify(pgid: EnvID, note: Note) -> Result<(), Error> {
+    let members = ENV_MANAGER.borrow_mut().group_members(pgid);
+    if members.is_empty() {
+        return Err(Error::BadEnv);
+    }
+    for id in members {
+        let ind = ENV_MANAGER.borrow_mut().envid2ind(id, 0);
+        if let Ok(ind) = ind {
+            env_notify(ind, note);
+        }
+    }
+    Ok(())
+}
+
+/// destroy every env of a process group, callers check permission
+pub fn env_group_destroy(pgid: EnvID) -> Result<(), Error> {
+    let members = ENV_MANAGER.borrow_mut().group_members(pgid);
+    if members.is_empty() {
+        return Err(Error::BadEnv);
+    }
+    for id in members {
+        let ind = ENV_MANAGER.borrow_mut().envid2ind(id, 0);
+        if let Ok(ind) = ind {
+            env_destroy(ind);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,106 @@
+use core::mem::size_of;
+
+use crate::clock::cycles_to_ticks;
+
+use super::Env;
+
+/// layout version of EnvInfo, bumped when fields change
+pub const ENV_INFO_VERSION: u32 = 1;
+
+/// read-only env record mapped at UENVS, shared with user space
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+pub struct EnvInfo {
+    pub version: u32,
+    pub env_id: u32,
+    pub env_parent_id: u32,
+    pub env_status: u32,
+    pub env_pri: u32,
+    pub env_runs: u32,
+    pub env_exit_code: i32,
+    pub env_ipc_value: u32,
+    pub env_ipc_from: u32,
+    pub env_ipc_perm: u32,
+    reserved: [u32; 6],
+}
+
+/// `struct EnvInfo` in include/env.h must match this layout
+const _: () = assert!(size_of::<EnvInfo>() == 64);
+
+impl EnvInfo {
+    /// create an empty record
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            version: ENV_INFO_VERSION,
+            env_id: 0,
+            env_parent_id: 0,
+            env_status: 0,
+            env_pri: 0,
+            env_runs: 0,
+            env_exit_code: 0,
+            env_ipc_value: 0,
+            env_ipc_from: 0,
+            env_ipc_perm: 0,
+            reserved: [0; 6]
+        }
+    }
+}
+
+impl<'a> From<&Env<'a>> for EnvInfo {
+    fn from(env: &Env<'a>) -> Self {
+        Self {
+            env_id: env.env_id.as_usize() as u32,
+            env_parent_id: env.env_parent_id.as_usize() as u32,
+            env_status: env.env_status as u32,
+            env_pri: env.env_pri as u32,
+            env_runs: env.env_runs as u32,
+            env_exit_code: env.env_exit_code,
+            env_ipc_value: env.env_ipc_value as u32,
+            env_ipc_from: env.env_ipc_from as u32,
+            env_ipc_perm: env.env_ipc_perm as u32,
+            ..Self::new()
+        }
+    }
+}
+
+/// max length of env name
+pub const ENV_NAME_LEN: usize = 16;
+/// max length of env command line, nul separated arguments
+pub const ENV_CMDLINE_MAX: usize = 256;
+
+/// snapshot of an env returned by sys_env_stat
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+pub struct EnvStat {
+    pub env_id: u32,
+    pub env_parent_id: u32,
+    pub env_status: u32,
+    pub env_pri: u32,
+    pub env_name: [u8; ENV_NAME_LEN],
+    pub env_runs: u32,
+    pub env_ticks: u32,
+    pub env_pages: u32,
+    pub env_ipc_recving: u32,
+    pub env_sleeping: u32,
+}
+
+/// `struct EnvStat` in include/env.h must match this layout
+const _: () = assert!(size_of::<EnvStat>() == 52);
+
+impl<'a> From<&Env<'a>> for EnvStat {
+    fn from(env: &Env<'a>) -> Self {
+        Self {
+            env_id: env.env_id.as_usize() as u32,
+            env_parent_id: env.env_parent_id.as_usize() as u32,
+            env_status: env.env_status as u32,
+            env_pri: env.env_pri as u32,
+            env_name: env.env_name,
+            env_runs: env.env_runs as u32,
+            env_ticks: cycles_to_ticks(env.env_rusage.utime + env.env_rusage.stime) as u32,
+            env_pages: env.env_pgdir.as_ref().map_or(0, |pgdir| pgdir.resident()) as u32,
+            env_ipc_recving: env.env_ipc_receiving as u32,
+            env_sleeping: env.env_sleeping as u32
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{debug, memory::{frame::{frame_snapshot, FrameSnapshot}, mmu::{PhysAddr, PhysPageNum, VirtAddr, UTOP, UVPT}, page_table::PageTable, shm::SHM_MANAGER}, sync::cell::UPSafeCell, warn};
+
+use super::{coredump::CORE, EnvManager, EnvStatus, ENV_MANAGER};
+
+/// problems described in the log per check, the rest are only counted
+const MEMCHECK_LOG_MAX: usize = 16;
+
+/// frame holders, a frame may have several
+const HOLD_KERNEL: u8 = 0x1;
+const HOLD_TABLE: u8 = 0x2;
+const HOLD_PAGE: u8 = 0x4;
+const HOLD_SHM: u8 = 0x8;
+const HOLD_INFO: u8 = 0x10;
+const HOLD_CORE: u8 = 0x20;
+
+/// check after every env free, set by the `memcheck=` boot option
+static MEMCHECK_ON_FREE: UPSafeCell<bool> = UPSafeCell::new(false);
+
+/// result of a frame ref count check, written by sys_memcheck
+#[derive(Clone, Copy, Default)]
+#[repr(C)]
+pub struct MemCheck {
+    pub mc_frames: u32,
+    /// frames something holds a ref on
+    pub mc_used: u32,
+    /// frames whose ref count differs from the refs held on them
+    pub mc_mismatches: u32,
+    /// frames nothing holds but are not free
+    pub mc_leaks: u32,
+    /// page tables held twice or also used as something else
+    pub mc_double_maps: u32,
+    /// ptes pointing past the last frame
+    pub mc_wild: u32
+}
+
+impl MemCheck {
+    /// number of problems found
+    pub fn problems(&self) -> usize {
+        (self.mc_mismatches + self.mc_leaks + self.mc_double_maps + self.mc_wild) as usize
+    }
+}
+
+/// ref counts recomputed from the holders of frames
+struct Expected {
+    refs: Vec<u16>,
+    holders: Vec<u8>,
+    wild: usize
+}
+
+impl Expected {
+    /// no refs held on nframes frames
+    fn new(nframes: usize) -> Self {
+        Self {
+            refs: vec![0; nframes],
+            holders: vec![0; nframes],
+            wild: 0
+        }
+    }
+
+    /// count a ref held on a frame
+    fn hold(&mut self, ppn: PhysPageNum, holder: u8) {
+        let ind = ppn.as_usize();
+        if ind < self.refs.len() {
+            self.refs[ind] = self.refs[ind].saturating_add(1);
+            self.holders[ind] |= holder;
+        } else {
+            self.wild += 1;
+        }
+    }
+
+    /// count refs held by a page dir below limit, one on each page table and one for each pte
+    fn hold_pgdir(&mut self, pgdir: &PageTable, limit: VirtAddr) {
+        for pde in pgdir.entries[..limit.pdx()].iter().filter(|pde| pde.valid()) {
+            self.hold(pde.ppn(), HOLD_TABLE);
+        }
+        for (_, pte) in pgdir.mapped(limit) {
+            self.hold(pte.ppn(), HOLD_PAGE);
+        }
+    }
+
+    /// compare with the frame allocator, log the first problems
+    fn compare(&self, snap: &FrameSnapshot) -> MemCheck {
+        let mut check = MemCheck {
+            mc_frames: self.refs.len() as u32,
+            mc_wild: self.wild as u32,
+            ..Default::default()
+        };
+        let mut logged = 0;
+        let mut report = |args: core::fmt::Arguments| {
+            if logged < MEMCHECK_LOG_MAX {
+                warn!("memcheck: {}", args);
+            }
+            logged += 1;
+        };
+        for (ind, &expected) in self.refs.iter().enumerate() {
+            let (actual, free, holders) = (snap.refs[ind], snap.free[ind], self.holders[ind]);
+            if expected == 0 {
+                if actual != 0 || !free {
+                    check.mc_leaks += 1;
+                    report(format_args!("frame {:x} leaked with ref {}{}", ind, actual, if free { "" } else { ", not free" }));
+                }
+                continue;
+            }
+            check.mc_used += 1;
+            if actual != expected || free {
+                check.mc_mismatches += 1;
+                report(format_args!("frame {:x} has ref {}, {} held{}", ind, actual, expected, if free { ", on free list" } else { "" }));
+            }
+            if holders & HOLD_TABLE != 0 && (holders != HOLD_TABLE || expected > 1) {
+                check.mc_double_maps += 1;
+                report(format_args!("page table frame {:x} held {} times by {:#x}", ind, expected, holders));
+            }
+        }
+        if check.mc_wild > 0 {
+            report(format_args!("{} ptes past the last frame", check.mc_wild));
+        }
+        check
+    }
+}
+
+impl<'a> EnvManager<'a> {
+    /// recompute the ref count of every frame from page dirs, page tables, shared memory,
+    /// env info pages and the core dump, and compare it with the frame allocator
+    pub fn memcheck(&self) -> MemCheck {
+        let snap = frame_snapshot();
+        let mut expected = Expected::new(snap.refs.len());
+        for ppn in 0..snap.reserved {
+            expected.hold(PhysPageNum::new(ppn), HOLD_KERNEL);
+        }
+        expected.hold_pgdir(&self.base_pgdir, UVPT);
+        for ppn in self.envs.info_pages() {
+            expected.hold(ppn, HOLD_INFO);
+        }
+        for ind in 0..self.envs.len() {
+            let env = &self.envs[ind];
+            if env.env_status == EnvStatus::Free {
+                continue;
+            }
+            if let Some(pgdir) = &env.env_pgdir {
+                expected.hold(PhysPageNum::from(PhysAddr::from_kva(VirtAddr::from_ptr(&**pgdir))), HOLD_TABLE);
+                expected.hold_pgdir(pgdir, UTOP);
+            }
+        }
+        for ppn in SHM_MANAGER.borrow_mut().frames() {
+            expected.hold(ppn, HOLD_SHM);
+        }
+        if let Some(core) = CORE.borrow_mut().as_ref() {
+            for &ppn in core.pages() {
+                expected.hold(ppn, HOLD_CORE);
+            }
+        }
+        let check = expected.compare(&snap);
+        if check.problems() == 0 {
+            debug!("memcheck: {} of {} frames used, ok", check.mc_used, check.mc_frames);
+        } else {
+            warn!("memcheck: {} mismatches, {} leaks, {} double maps, {} wild ptes",
+                check.mc_mismatches, check.mc_leaks, check.mc_double_maps, check.mc_wild);
+        }
+        check
+    }
+}
+
+/// set whether the checker runs after every env free
+pub fn memcheck_init(on_free: bool) {
+    *MEMCHECK_ON_FREE.borrow_mut() = on_free;
+}
+
+/// check if the checker runs after every env free
+pub fn memcheck_on_free() -> bool {
+    *MEMCHECK_ON_FREE.borrow_mut()
+}
+
+/// check frame ref counts of the whole system
+pub fn memcheck() -> MemCheck {
+    ENV_MANAGER.borrow_mut().memcheck()
+}
@@ -0,0 +1,53 @@
+use crate::warn;
+
+use super::{coredump::core_release, env_destroy, EnvID, EnvManager, EnvStatus, ENV_MANAGER, EXIT_OOM};
+
+impl<'a> EnvManager<'a> {
+    /// pick the env to kill when frames run out.
+    /// resident pages are weighted down by priority, protected envs are never picked.
+    fn oom_victim(&self) -> Option<(usize, usize)> {
+        let mut victim = None;
+        let mut worst = (0, 0);
+        for ind in 0..self.envs.len() {
+            let env = &self.envs[ind];
+            if env.env_status == EnvStatus::Free || env.env_oom_protected {
+                continue;
+            }
+            let rss = env.env_pgdir.as_ref().map_or(0, |pgdir| pgdir.resident());
+            let badness = (rss / env.env_pri.max(1), rss);
+            if rss > 0 && badness > worst {
+                worst = badness;
+                victim = Some((ind, rss));
+            }
+        }
+        victim
+    }
+}
+
+/// exempt an env from the oom killer
+pub fn oom_protect(envid: EnvID) {
+    let mut em = ENV_MANAGER.borrow_mut();
+    let ind = envid.envx();
+    if em.envs[ind].env_id == envid {
+        em.envs[ind].env_oom_protected = true;
+    }
+}
+
+/// free frames by dropping the held core dump or destroying an env, panic if there is nothing
+/// left to kill. does not return if the victim is the current env.
+pub fn oom_kill() {
+    if core_release() {
+        warn!("oom: dropping core dump");
+        return;
+    }
+    let mut em = ENV_MANAGER.borrow_mut();
+    let (ind, rss) = match em.oom_victim() {
+        Some(victim) => victim,
+        None => panic!("out of memory and no env to kill")
+    };
+    let env = &mut em.envs[ind];
+    warn!("oom: killing [{}] with {} resident pages", env, rss);
+    env.env_exit_code = EXIT_OOM;
+    drop(em);
+    env_destroy(ind);
+}
@@ -0,0 +1,100 @@
+use crate::{exception::traps::{Trapframe, STATUS_UM}, sync::cell::UPSafeCell, util::ring_buffer::RingBuffer};
+
+use super::{EnvID, ENV_MANAGER};
+
+/// samples kept until they are read, new samples are dropped when full
+pub const PROF_BUF_LEN: usize = 2048;
+
+/// global sampling profiler
+pub static PROFILER: UPSafeCell<Profiler> = UPSafeCell::new(Profiler::new());
+
+/// where a timer interrupt found the cpu, read by sys_prof_read
+#[derive(Clone, Copy, Default)]
+#[repr(C)]
+pub struct ProfSample {
+    pub ps_epc: u32,
+    /// current env, zero when idle
+    pub ps_envid: u32,
+    pub ps_asid: u32,
+    /// nonzero if the interrupt was taken in kernel mode
+    pub ps_kernel: u32
+}
+
+/// sample buffer of the profiler
+pub struct Profiler {
+    samples: RingBuffer<ProfSample, PROF_BUF_LEN>,
+    running: bool,
+    target: EnvID,
+    dropped: usize
+}
+
+impl Profiler {
+    /// create a stopped profiler
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            samples: RingBuffer::new(ProfSample {
+                ps_epc: 0,
+                ps_envid: 0,
+                ps_asid: 0,
+                ps_kernel: 0
+            }),
+            running: false,
+            target: EnvID::new(0),
+            dropped: 0
+        }
+    }
+
+    /// drop old samples and sample env from now on, zero samples every env and the idle kernel
+    pub fn start(&mut self, target: EnvID) {
+        self.samples.clear();
+        self.running = true;
+        self.target = target;
+        self.dropped = 0;
+    }
+
+    /// stop sampling, return number of samples dropped since start
+    pub fn stop(&mut self) -> usize {
+        self.running = false;
+        self.dropped
+    }
+
+    /// take the oldest samples into buf, return how many were taken
+    pub fn drain(&mut self, buf: &mut [ProfSample]) -> usize {
+        let mut n = 0;
+        while n < buf.len() {
+            match self.samples.pop() {
+                Some(sample) => buf[n] = sample,
+                None => break
+            }
+            n += 1;
+        }
+        n
+    }
+}
+
+/// record where the timer interrupt in tf was taken
+pub fn profile_sample(tf: &Trapframe) {
+    let mut prof = PROFILER.borrow_mut();
+    if !prof.running {
+        return;
+    }
+    let em = ENV_MANAGER.borrow_mut();
+    let (envid, asid) = match em.cur_env_ind {
+        Some(ind) => (em.envs[ind].env_id, em.envs[ind].env_asid.as_usize()),
+        None => (EnvID::zero(), 0)
+    };
+    drop(em);
+    if prof.target.as_usize() != 0 && prof.target != envid {
+        return;
+    }
+    let sample = ProfSample {
+        ps_epc: tf.cp0_epc as u32,
+        ps_envid: envid.as_usize() as u32,
+        ps_asid: asid as u32,
+        ps_kernel: (tf.cp0_status & STATUS_UM == 0) as u32
+    };
+    if prof.samples.push(sample).is_err() {
+        prof.dropped += 1;
+    }
+}
@@ -0,0 +1,245 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{err::Error, exception::traps::{Trapframe, STATUS_UM}, gdb::{gdb_attached, gdb_trap, SIGTRAP}, memory::mmu::{VirtAddr, PAGE_SIZE}, monitor::monitor};
+
+use super::{coredump::env_crash, env_enter_kernel, env_leave_kernel, env_sched, env_sleep, EnvID, EnvManager, EnvStatus, ENV_MANAGER};
+
+/// attach to an env, it stops with STOP_ATTACH
+pub const PT_ATTACH: usize = 0;
+/// detach from an env and let it run
+pub const PT_DETACH: usize = 1;
+/// read memory of an env
+pub const PT_READ: usize = 2;
+/// write memory of an env
+pub const PT_WRITE: usize = 3;
+/// get trap frame of an env
+pub const PT_GETREGS: usize = 4;
+/// set trap frame of an env, its status register is kept
+pub const PT_SETREGS: usize = 5;
+/// continue a stopped env
+pub const PT_CONT: usize = 6;
+/// continue a stopped env for one instruction
+pub const PT_STEP: usize = 7;
+/// wait for an env to stop, return the stop reason
+pub const PT_WAIT: usize = 8;
+/// stop a running env
+pub const PT_STOP: usize = 9;
+
+/// stopped by PT_ATTACH
+pub const STOP_ATTACH: usize = 1;
+/// stopped at a break instruction of its own
+pub const STOP_BREAK: usize = 2;
+/// stopped after PT_STEP
+pub const STOP_STEP: usize = 3;
+/// stopped by PT_STOP
+pub const STOP_REQUEST: usize = 4;
+
+/// most bytes moved by one PT_READ or PT_WRITE
+pub const PT_XFER_MAX: usize = PAGE_SIZE;
+
+/// `break` instruction
+const BREAK_INSN: u32 = 0x0000_000d;
+
+/// instruction replaced by a step breakpoint
+#[derive(Clone, Copy)]
+pub struct StepBreak {
+    va: VirtAddr,
+    insn: u32
+}
+
+/// addresses an instruction may pass control to, a branch has two
+fn successors(tf: &Trapframe, insn: u32) -> [Option<usize>; 2] {
+    let pc = tf.cp0_epc;
+    let op = insn >> 26;
+    let rs = ((insn >> 21) & 0x1f) as usize;
+    let funct = insn & 0x3f;
+    let offset = ((insn & 0xffff) as i16 as isize) << 2;
+    match op {
+        // jr, jalr
+        0 if funct == 8 || funct == 9 => [Some(tf.regs[rs]), None],
+        // j, jal
+        2 | 3 => [Some(((pc + 4) & 0xf000_0000) | ((insn & 0x03ff_ffff) << 2) as usize), None],
+        // regimm, beq, bne, blez, bgtz and their likely forms
+        1 | 4..=7 | 20..=23 => {
+            let taken = (pc + 4).wrapping_add_signed(offset);
+            [Some(pc + 8), if taken != pc + 8 { Some(taken) } else { None }]
+        },
+        _ => [Some(pc + 4), None]
+    }
+}
+
+impl<'a> EnvManager<'a> {
+    /// index of an env traced by current env
+    pub fn tracee(&self, envid: EnvID) -> Result<usize, Error> {
+        let ind = self.envid2ind(envid, 0)?;
+        let cur_id = self.envs[self.cur_env_ind.unwrap()].env_id;
+        if self.envs[ind].env_tracer != cur_id {
+            return Err(Error::BadEnv);
+        }
+        Ok(ind)
+    }
+
+    /// start tracing env by current env, the env stops
+    pub fn trace_attach(&mut self, ind: usize) -> Result<(), Error> {
+        let cur = self.cur_env_ind.unwrap();
+        if ind == cur || self.envs[ind].env_tracer.as_usize() != 0 {
+            return Err(Error::Inval);
+        }
+        self.envs[ind].env_tracer = self.envs[cur].env_id;
+        self.envs[cur].env_tracees += 1;
+        self.trace_stop(ind, STOP_ATTACH);
+        Ok(())
+    }
+
+    /// stop tracing env, it runs again if stopped
+    pub fn trace_detach(&mut self, ind: usize) {
+        self.clear_step_breaks(ind);
+        if let Ok(tracer) = self.envid2ind(self.envs[ind].env_tracer, 0) {
+            self.envs[tracer].env_tracees -= 1;
+        }
+        self.envs[ind].env_tracer = EnvID::zero();
+        if self.envs[ind].env_trace_stop != 0 {
+            self.trace_resume(ind);
+        }
+    }
+
+    /// stop a traced env and wake up its tracer if it is waiting
+    pub fn trace_stop(&mut self, ind: usize, reason: usize) {
+        self.envs[ind].env_trace_stop = reason;
+        self.block(ind);
+        if let Ok(tracer) = self.envid2ind(self.envs[ind].env_tracer, 0) {
+            if self.envs[tracer].env_sleeping {
+                self.unblock(tracer);
+            }
+        }
+    }
+
+    /// let a stopped env run, it stays stopped if a note stopped it too
+    pub fn trace_resume(&mut self, ind: usize) {
+        self.envs[ind].env_trace_stop = 0;
+        self.resume(ind);
+    }
+
+    /// release tracing state of an env being freed
+    pub fn trace_release(&mut self, ind: usize) {
+        let tracer = self.envs[ind].env_tracer;
+        if tracer.as_usize() != 0 {
+            if let Ok(tracer) = self.envid2ind(tracer, 0) {
+                self.envs[tracer].env_tracees -= 1;
+                if self.envs[tracer].env_sleeping {
+                    self.unblock(tracer);
+                }
+            }
+            self.envs[ind].env_tracer = EnvID::zero();
+        }
+        if self.envs[ind].env_tracees != 0 {
+            let id = self.envs[ind].env_id;
+            for i in 0..self.envs.len() {
+                if self.envs[i].env_status != EnvStatus::Free && self.envs[i].env_tracer == id {
+                    self.trace_detach(i);
+                }
+            }
+        }
+    }
+
+    /// read memory of env
+    pub fn trace_read(&self, ind: usize, va: VirtAddr, len: usize) -> Result<Vec<u8>, Error> {
+        let mut buf = vec![0; len];
+        match &self.envs[ind].env_pgdir {
+            Some(pgdir) => pgdir.copy_in(va, &mut buf)?,
+            None => return Err(Error::BadEnv)
+        }
+        Ok(buf)
+    }
+
+    /// write memory of env, shared frames are copied before they are written
+    pub fn trace_write(&mut self, ind: usize, va: VirtAddr, data: &[u8]) -> Result<(), Error> {
+        let env = &mut self.envs[ind];
+        let asid = env.env_asid;
+        let pgdir = env.env_pgdir.as_mut().ok_or(Error::BadEnv)?;
+        let mut page = va.page_align_down();
+        while page < va + data.len() {
+            pgdir.make_private(asid, page)?;
+            page += PAGE_SIZE;
+        }
+        pgdir.copy_out(va, data)
+    }
+
+    /// plant breaks after the instruction env stopped at
+    pub fn plant_step_breaks(&mut self, ind: usize) -> Result<(), Error> {
+        let epc = VirtAddr::new(self.envs[ind].env_tf.cp0_epc);
+        let insn = u32::from_ne_bytes(self.trace_read(ind, epc, 4)?.try_into().unwrap());
+        let targets = successors(&self.envs[ind].env_tf, insn);
+        for (slot, target) in targets.iter().enumerate() {
+            let va = match target {
+                Some(va) => VirtAddr::new(*va),
+                None => continue
+            };
+            let saved = self.trace_read(ind, va, 4).and_then(|orig| {
+                self.trace_write(ind, va, &BREAK_INSN.to_ne_bytes())?;
+                Ok(u32::from_ne_bytes(orig.try_into().unwrap()))
+            });
+            match saved {
+                Ok(insn) => self.envs[ind].env_step_breaks[slot] = Some(StepBreak { va, insn }),
+                Err(err) => {
+                    self.clear_step_breaks(ind);
+                    return Err(err);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// restore instructions replaced by step breaks
+    pub fn clear_step_breaks(&mut self, ind: usize) {
+        for slot in 0..self.envs[ind].env_step_breaks.len() {
+            if let Some(b) = self.envs[ind].env_step_breaks[slot].take() {
+                let _ = self.trace_write(ind, b.va, &b.insn.to_ne_bytes());
+            }
+        }
+    }
+}
+
+/// breakpoint exception handler. a break in kernel mode enters the monitor,
+/// a traced env stops and its tracer is woken up, an untraced env is reported to gdb or crashes.
+#[no_mangle]
+pub extern "C" fn do_breakpoint(tf: &mut Trapframe) {
+    if tf.cp0_status & STATUS_UM == 0 {
+        monitor("break", Some(tf), true);
+        tf.cp0_epc += 4;
+        return;
+    }
+    env_enter_kernel();
+    let mut em = ENV_MANAGER.borrow_mut();
+    let ind = em.cur_env_ind.unwrap();
+    let epc = VirtAddr::new(tf.cp0_epc);
+    if em.envs[ind].env_tracer.as_usize() == 0 && gdb_attached() {
+        drop(em);
+        gdb_trap(SIGTRAP);
+        env_leave_kernel();
+        return;
+    }
+    if em.envs[ind].env_tracer.as_usize() == 0 {
+        drop(em);
+        env_crash(SIGTRAP);
+        return;
+    }
+    let stepped = em.envs[ind].env_step_breaks.iter().flatten().any(|b| b.va == epc);
+    em.clear_step_breaks(ind);
+    em.trace_stop(ind, if stepped { STOP_STEP } else { STOP_BREAK });
+    drop(em);
+    env_sched(1);
+}
+
+/// wait until a traced env stops, return the stop reason
+pub fn trace_wait(envid: EnvID) -> Result<usize, Error> {
+    let em = ENV_MANAGER.borrow_mut();
+    let ind = em.tracee(envid)?;
+    let reason = em.envs[ind].env_trace_stop;
+    if reason != 0 {
+        return Ok(reason);
+    }
+    drop(em);
+    env_sleep();
+    env_sched(1);
+}
@@ -0,0 +1,79 @@
+use crate::err::Error;
+
+/// number of limited resources
+pub const NRLIMIT: usize = 4;
+/// no limit on a resource
+pub const RLIM_INFINITY: usize = i32::MAX as usize;
+
+/// resources limited per env
+#[repr(usize)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Resource {
+    /// resident pages below UTOP
+    Frames = 0,
+    /// live children
+    Children = 1,
+    /// attached shared memory segments
+    Shm = 2,
+    /// ticks in user and kernel mode
+    CpuTicks = 3
+}
+
+/// convert int to resource
+impl TryFrom<usize> for Resource {
+    type Error = Error;
+
+    fn try_from(value: usize) -> Result<Self, Self::Error> {
+        match value {
+            x if x == Resource::Frames as usize => Ok(Resource::Frames),
+            x if x == Resource::Children as usize => Ok(Resource::Children),
+            x if x == Resource::Shm as usize => Ok(Resource::Shm),
+            x if x == Resource::CpuTicks as usize => Ok(Resource::CpuTicks),
+            _ => Err(Error::Inval)
+        }
+    }
+}
+
+/// resource limits of an env, inherited on fork
+#[derive(Clone, Copy)]
+pub struct Rlimit {
+    limits: [usize; NRLIMIT]
+}
+
+impl Rlimit {
+    /// create limits with every resource unlimited
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            limits: [RLIM_INFINITY; NRLIMIT]
+        }
+    }
+
+    /// limit of resource
+    #[inline]
+    pub fn get(&self, res: Resource) -> usize {
+        self.limits[res as usize]
+    }
+
+    /// set limit of resource, values above infinity mean no limit
+    #[inline]
+    pub fn set(&mut self, res: Resource, value: usize) {
+        self.limits[res as usize] = value.min(RLIM_INFINITY);
+    }
+
+    /// whether resource is limited at all
+    #[inline]
+    pub fn is_limited(&self, res: Resource) -> bool {
+        self.get(res) != RLIM_INFINITY
+    }
+
+    /// check that one more unit of resource fits when used units are in use
+    #[inline]
+    pub fn check(&self, res: Resource, used: usize, err: Error) -> Result<(), Error> {
+        if used >= self.get(res) {
+            Err(err)
+        } else {
+            Ok(())
+        }
+    }
+}
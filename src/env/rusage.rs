@@ -0,0 +1,92 @@
+use crate::clock::cycles_to_ticks;
+
+use super::syscall::SyscallID;
+
+/// number of syscall ids
+pub const NSYSCALL: usize = SyscallID::SysNo as usize;
+
+/// getrusage target: the calling env
+pub const RUSAGE_SELF: usize = 0;
+/// getrusage target: freed children of the calling env
+pub const RUSAGE_CHILDREN: usize = 1;
+
+/// resource usage of an env
+#[derive(Clone, Copy)]
+pub struct Rusage {
+    /// cycles spent in user mode
+    pub utime: u64,
+    /// cycles spent in kernel mode
+    pub stime: u64,
+    /// peak resident pages
+    pub maxrss: usize,
+    /// tlb refill exceptions
+    pub tlb_refills: usize,
+    /// pages allocated on tlb refill
+    pub passive_allocs: usize,
+    /// successful ipc sends
+    pub ipc_sends: usize,
+    /// ipc receives
+    pub ipc_recvs: usize,
+    /// syscall counts by id
+    pub syscalls: [u32; NSYSCALL],
+}
+
+impl Rusage {
+    /// create an empty record
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            utime: 0,
+            stime: 0,
+            maxrss: 0,
+            tlb_refills: 0,
+            passive_allocs: 0,
+            ipc_sends: 0,
+            ipc_recvs: 0,
+            syscalls: [0; NSYSCALL]
+        }
+    }
+
+    /// add usage of another env, peak resident pages is the larger one
+    pub fn add(&mut self, other: &Rusage) {
+        self.utime += other.utime;
+        self.stime += other.stime;
+        self.maxrss = self.maxrss.max(other.maxrss);
+        self.tlb_refills += other.tlb_refills;
+        self.passive_allocs += other.passive_allocs;
+        self.ipc_sends += other.ipc_sends;
+        self.ipc_recvs += other.ipc_recvs;
+        for (dst, src) in self.syscalls.iter_mut().zip(other.syscalls.iter()) {
+            *dst += *src;
+        }
+    }
+}
+
+/// resource usage returned by sys_getrusage
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct RusageInfo {
+    pub ru_utime: u32,
+    pub ru_stime: u32,
+    pub ru_maxrss: u32,
+    pub ru_tlb_refills: u32,
+    pub ru_passive_allocs: u32,
+    pub ru_ipc_sends: u32,
+    pub ru_ipc_recvs: u32,
+    pub ru_syscalls: [u32; NSYSCALL],
+}
+
+impl From<&Rusage> for RusageInfo {
+    fn from(ru: &Rusage) -> Self {
+        Self {
+            ru_utime: cycles_to_ticks(ru.utime) as u32,
+            ru_stime: cycles_to_ticks(ru.stime) as u32,
+            ru_maxrss: ru.maxrss as u32,
+            ru_tlb_refills: ru.tlb_refills as u32,
+            ru_passive_allocs: ru.passive_allocs as u32,
+            ru_ipc_sends: ru.ipc_sends as u32,
+            ru_ipc_recvs: ru.ipc_recvs as u32,
+            ru_syscalls: ru.syscalls
+        }
+    }
+}
@@ -0,0 +1,211 @@
+use core::fmt::{self, Display};
+
+use crate::{clock::uptime_ticks, err::Error, println, sync::cell::UPSafeCell, util::ring_buffer::RingBuffer, warn};
+
+use super::{syscall::SyscallID, Env, ENV_MANAGER};
+
+/// record syscalls of env into the kernel ring buffer
+pub const STRACE_RECORD: usize = 1;
+/// print syscalls of env on console
+pub const STRACE_CONSOLE: usize = 2;
+/// envs forked by env are traced with the same flags
+pub const STRACE_FOLLOW: usize = 4;
+/// all tracing flags
+pub const STRACE_MASK: usize = STRACE_RECORD | STRACE_CONSOLE | STRACE_FOLLOW;
+
+/// records kept in the ring buffer, the oldest is dropped when full
+pub const STRACE_BUF_LEN: usize = 256;
+
+/// global syscall trace buffer
+pub static STRACE: UPSafeCell<Strace> = UPSafeCell::new(Strace::new());
+
+/// one completed syscall, read by sys_strace_read
+#[derive(Clone, Copy, Default)]
+#[repr(C)]
+pub struct SyscallRecord {
+    pub sr_envid: u32,
+    pub sr_sysno: u32,
+    pub sr_args: [u32; 5],
+    pub sr_ret: i32,
+    pub sr_tick: u32
+}
+
+/// syscall trace buffer and boot time filter
+pub struct Strace {
+    records: RingBuffer<SyscallRecord, STRACE_BUF_LEN>,
+    dropped: usize,
+    boot: &'static str
+}
+
+/// name and argument count of a syscall
+fn syscall_desc(id: &SyscallID) -> (&'static str, usize) {
+    match id {
+        SyscallID::Putchar => ("putchar", 1),
+        SyscallID::PrintCons => ("print_cons", 2),
+        SyscallID::GetEnvID => ("getenvid", 0),
+        SyscallID::Yield => ("yield", 0),
+        SyscallID::EnvDestroy => ("env_destroy", 1),
+        SyscallID::SetTlbModEntry => ("set_tlb_mod_entry", 2),
+        SyscallID::MemAlloc => ("mem_alloc", 3),
+        SyscallID::MemMap => ("mem_map", 5),
+        SyscallID::MemUnmap => ("mem_unmap", 2),
+        SyscallID::Exofork => ("exofork", 0),
+        SyscallID::SetEnvStatus => ("set_env_status", 2),
+        SyscallID::SetTrapframe => ("set_trapframe", 2),
+        SyscallID::Panic => ("panic", 1),
+        SyscallID::IpcTrySend => ("ipc_try_send", 4),
+        SyscallID::IpcRecv => ("ipc_recv", 1),
+        SyscallID::CGetC => ("cgetc", 0),
+        SyscallID::WriteDev => ("write_dev", 3),
+        SyscallID::ReadDev => ("read_dev", 3),
+        SyscallID::ShmGet => ("shmget", 2),
+        SyscallID::ShmAt => ("shmat", 3),
+        SyscallID::ShmDt => ("shmdt", 2),
+        SyscallID::ShmCtl => ("shmctl", 2),
+        SyscallID::SemOpen => ("semopen", 2),
+        SyscallID::SemWait => ("semwait", 1),
+        SyscallID::SemPost => ("sempost", 1),
+        SyscallID::SemKill => ("semkill", 1),
+        SyscallID::ConsoleRead => ("console_read", 2),
+        SyscallID::TtyIoctl => ("tty_ioctl", 2),
+        SyscallID::EnvNotify => ("env_notify", 2),
+        SyscallID::SetNotifyEntry => ("set_notify_entry", 2),
+        SyscallID::TimeOfDay => ("time_of_day", 1),
+        SyscallID::SetTimeOfDay => ("set_time_of_day", 1),
+        SyscallID::GetTrapframe => ("get_trapframe", 2),
+        SyscallID::EnvList => ("env_list", 2),
+        SyscallID::EnvStat => ("env_stat", 2),
+        SyscallID::GetRusage => ("getrusage", 2),
+        SyscallID::GetRlimit => ("get_rlimit", 2),
+        SyscallID::SetRlimit => ("set_rlimit", 3),
+        SyscallID::SetCmdline => ("set_cmdline", 3),
+        SyscallID::GetCmdline => ("get_cmdline", 3),
+        SyscallID::SetPgid => ("set_pgid", 2),
+        SyscallID::GetPgid => ("get_pgid", 1),
+        SyscallID::SetSid => ("set_sid", 0),
+        SyscallID::GetSid => ("get_sid", 1),
+        SyscallID::GroupNotify => ("group_notify", 2),
+        SyscallID::GroupDestroy => ("group_destroy", 1),
+        SyscallID::EnvDestroyTree => ("env_destroy_tree", 1),
+        SyscallID::SetTeardown => ("set_teardown", 2),
+        SyscallID::Ptrace => ("ptrace", 5),
+        SyscallID::CoreInfo => ("core_info", 1),
+        SyscallID::CoreRead => ("core_read", 3),
+        SyscallID::CoreClear => ("core_clear", 0),
+        SyscallID::Strace => ("strace", 2),
+        SyscallID::StraceRead => ("strace_read", 2),
+        SyscallID::KlogRead => ("klog_read", 2),
+        SyscallID::ProfStart => ("prof_start", 1),
+        SyscallID::ProfStop => ("prof_stop", 0),
+        SyscallID::ProfRead => ("prof_read", 2),
+        SyscallID::TlbRead => ("tlb_read", 2),
+        SyscallID::MemCheck => ("memcheck", 1),
+        SyscallID::CoreWait => ("core_wait", 0),
+        SyscallID::SysNo => ("?", 5)
+    }
+}
+
+impl Display for SyscallRecord {
+    /// format as `name(args) = ret`, error returns are decoded
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, nargs) = syscall_desc(&SyscallID::from(self.sr_sysno as usize));
+        write!(f, "{}(", name)?;
+        for (i, arg) in self.sr_args[..nargs].iter().enumerate() {
+            write!(f, "{}{:#x}", if i == 0 { "" } else { ", " }, arg)?;
+        }
+        write!(f, ") = {}", self.sr_ret)?;
+        if let Ok(err) = Error::try_from(self.sr_ret) {
+            write!(f, " {:?}", err)?;
+        }
+        Ok(())
+    }
+}
+
+impl Strace {
+    /// create an empty trace buffer
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            records: RingBuffer::new(SyscallRecord {
+                sr_envid: 0,
+                sr_sysno: 0,
+                sr_args: [0; 5],
+                sr_ret: 0,
+                sr_tick: 0
+            }),
+            dropped: 0,
+            boot: ""
+        }
+    }
+
+    /// append a record, dropping the oldest one if full
+    fn push(&mut self, rec: SyscallRecord) {
+        if self.records.is_full() {
+            self.dropped += 1;
+        }
+        self.records.push_overwrite(rec);
+    }
+
+    /// take the oldest records into buf, return how many were taken
+    pub fn drain(&mut self, buf: &mut [SyscallRecord]) -> usize {
+        let mut n = 0;
+        while n < buf.len() {
+            match self.records.pop() {
+                Some(rec) => buf[n] = rec,
+                None => break
+            }
+            n += 1;
+        }
+        if n > 0 && self.dropped > 0 {
+            warn!("strace: {} records dropped", self.dropped);
+            self.dropped = 0;
+        }
+        n
+    }
+
+    /// flags of an env named name given by the `strace=` boot option
+    fn boot_flags(&self, name: &str) -> usize {
+        if self.boot.split(',').any(|n| n == "*" || n == name) {
+            STRACE_CONSOLE
+        } else {
+            0
+        }
+    }
+}
+
+impl<'a> Env<'a> {
+    /// trace env from now on if its name is listed in the `strace=` boot option
+    pub fn strace_boot(&mut self) {
+        self.env_strace |= STRACE.borrow_mut().boot_flags(self.name());
+    }
+}
+
+/// set the comma separated env names traced on console from their start, `*` traces every env
+pub fn strace_init(names: &'static str) {
+    STRACE.borrow_mut().boot = names;
+}
+
+/// trace a syscall of current env that has returned
+pub fn strace_syscall(sysno: usize, args: [usize; 5], ret: i32) {
+    let em = ENV_MANAGER.borrow_mut();
+    let env = match em.cur_env_ind {
+        Some(ind) => &em.envs[ind],
+        None => return
+    };
+    if env.env_strace & (STRACE_RECORD | STRACE_CONSOLE) == 0 {
+        return;
+    }
+    let rec = SyscallRecord {
+        sr_envid: env.env_id.as_usize() as u32,
+        sr_sysno: sysno as u32,
+        sr_args: args.map(|a| a as u32),
+        sr_ret: ret,
+        sr_tick: uptime_ticks() as u32
+    };
+    if env.env_strace & STRACE_CONSOLE != 0 {
+        println!("[{}] {}", env, rec);
+    }
+    if env.env_strace & STRACE_RECORD != 0 {
+        STRACE.borrow_mut().push(rec);
+    }
+}
@@ -1,9 +1,11 @@
 use core::{borrow::BorrowMut, ffi::CStr, mem::{self, size_of}, ptr::write_volatile, slice, usize};
 
+use alloc::{vec, vec::Vec};
 
-use crate::{device::DeviceManager, env::{env_destroy, env_sched, envid2ind, get_cur_env_id, EnvID}, err::Error, exception::traps::Trapframe, memory::{frame::frame_alloc, mmu::{PhysAddr, VirtAddr, KSTACKTOP, PTE_V, UTEMP, UTOP}, shm::{shm_at, shm_dt, shm_get, shm_rmid, ShmCtl}}, print::{printcharc, scancharc}, println, try_or_return};
 
-use super::{sem::SEM_MAMANER, EnvStatus, ENV_MANAGER};
+use crate::{debug, device::{rtc::{time_of_day, TimeVal, RTC}, tty::{TtyCmd, TTY}, DeviceManager}, env::{env_destroy, env_destroy_tree, env_notify, env_sched, envid2ind, get_cur_env_id, EnvID, Note}, err::Error, exception::traps::Trapframe, log::{LOG, LOG_BUF_LEN}, memory::{frame::frame_alloc, mmu::{PhysAddr, VirtAddr, KSTACKTOP, PTE_V, UTEMP, UTOP}, shm::{shm_at, shm_dt, shm_get, shm_rmid, ShmCtl, SHMMNI}, tlb::{tlb_entries, tlb_read, TlbEntry}}, print::{printcharc, scancharc}, try_or_return};
+
+use super::{coredump::{core_release, CoreInfo, CORE, CORE_WAITERS, CORE_XFER_MAX}, cur_rusage, profile::{ProfSample, PROFILER, PROF_BUF_LEN}, strace::{strace_syscall, SyscallRecord, STRACE, STRACE_BUF_LEN, STRACE_FOLLOW, STRACE_MASK}, env_enter_kernel, env_leave_kernel, group::{env_group_destroy, env_group_notify}, info::{EnvStat, ENV_CMDLINE_MAX}, memcheck::{memcheck, MemCheck}, ptrace::*, rlimit::Resource, rusage::{RusageInfo, NSYSCALL, RUSAGE_CHILDREN, RUSAGE_SELF}, sem::SEM_MAMANER, EnvStatus, ENV_MANAGER, NENV};
 
 /// syscall id enum
 #[repr(usize)]
@@ -34,6 +36,41 @@ pub enum SyscallID {
 	SemWait,
 	SemPost,
 	SemKill,
+	ConsoleRead,
+	TtyIoctl,
+	EnvNotify,
+	SetNotifyEntry,
+	TimeOfDay,
+	SetTimeOfDay,
+	GetTrapframe,
+	EnvList,
+	EnvStat,
+	GetRusage,
+	GetRlimit,
+	SetRlimit,
+	SetCmdline,
+	GetCmdline,
+	SetPgid,
+	GetPgid,
+	SetSid,
+	GetSid,
+	GroupNotify,
+	GroupDestroy,
+	EnvDestroyTree,
+	SetTeardown,
+	Ptrace,
+	CoreInfo,
+	CoreRead,
+	CoreClear,
+	Strace,
+	StraceRead,
+	KlogRead,
+	ProfStart,
+	ProfStop,
+	ProfRead,
+	TlbRead,
+	MemCheck,
+	CoreWait,
 	SysNo,
 }
 
@@ -67,6 +104,41 @@ impl From<usize> for SyscallID {
 			x if x == SyscallID::SemWait as usize => SyscallID::SemWait,
 			x if x == SyscallID::SemPost as usize => SyscallID::SemPost,
 			x if x == SyscallID::SemKill as usize => SyscallID::SemKill,
+			x if x == SyscallID::ConsoleRead as usize => SyscallID::ConsoleRead,
+			x if x == SyscallID::TtyIoctl as usize => SyscallID::TtyIoctl,
+			x if x == SyscallID::EnvNotify as usize => SyscallID::EnvNotify,
+			x if x == SyscallID::SetNotifyEntry as usize => SyscallID::SetNotifyEntry,
+			x if x == SyscallID::TimeOfDay as usize => SyscallID::TimeOfDay,
+			x if x == SyscallID::SetTimeOfDay as usize => SyscallID::SetTimeOfDay,
+			x if x == SyscallID::GetTrapframe as usize => SyscallID::GetTrapframe,
+			x if x == SyscallID::EnvList as usize => SyscallID::EnvList,
+			x if x == SyscallID::EnvStat as usize => SyscallID::EnvStat,
+			x if x == SyscallID::GetRusage as usize => SyscallID::GetRusage,
+			x if x == SyscallID::GetRlimit as usize => SyscallID::GetRlimit,
+			x if x == SyscallID::SetRlimit as usize => SyscallID::SetRlimit,
+			x if x == SyscallID::SetCmdline as usize => SyscallID::SetCmdline,
+			x if x == SyscallID::GetCmdline as usize => SyscallID::GetCmdline,
+			x if x == SyscallID::SetPgid as usize => SyscallID::SetPgid,
+			x if x == SyscallID::GetPgid as usize => SyscallID::GetPgid,
+			x if x == SyscallID::SetSid as usize => SyscallID::SetSid,
+			x if x == SyscallID::GetSid as usize => SyscallID::GetSid,
+			x if x == SyscallID::GroupNotify as usize => SyscallID::GroupNotify,
+			x if x == SyscallID::GroupDestroy as usize => SyscallID::GroupDestroy,
+			x if x == SyscallID::EnvDestroyTree as usize => SyscallID::EnvDestroyTree,
+			x if x == SyscallID::SetTeardown as usize => SyscallID::SetTeardown,
+			x if x == SyscallID::Ptrace as usize => SyscallID::Ptrace,
+			x if x == SyscallID::CoreInfo as usize => SyscallID::CoreInfo,
+			x if x == SyscallID::CoreRead as usize => SyscallID::CoreRead,
+			x if x == SyscallID::CoreClear as usize => SyscallID::CoreClear,
+			x if x == SyscallID::Strace as usize => SyscallID::Strace,
+			x if x == SyscallID::StraceRead as usize => SyscallID::StraceRead,
+			x if x == SyscallID::KlogRead as usize => SyscallID::KlogRead,
+			x if x == SyscallID::ProfStart as usize => SyscallID::ProfStart,
+			x if x == SyscallID::ProfStop as usize => SyscallID::ProfStop,
+			x if x == SyscallID::ProfRead as usize => SyscallID::ProfRead,
+			x if x == SyscallID::TlbRead as usize => SyscallID::TlbRead,
+			x if x == SyscallID::MemCheck as usize => SyscallID::MemCheck,
+			x if x == SyscallID::CoreWait as usize => SyscallID::CoreWait,
 			_ => SyscallID::SysNo
 		}
 	}
@@ -101,11 +173,241 @@ fn sys_yield() -> ! {
 }
 /// destroy env
 fn sys_env_destroy(envid: EnvID) -> i32 {
-	let ind = try_or_return!(envid2ind(envid, 1));
-	println!("[{:x}] destroying {:x}", get_cur_env_id().unwrap_or_default(), envid);
+	let em = ENV_MANAGER.borrow_mut();
+	let ind = try_or_return!(em.envid2ind(envid, 1));
+	debug!("[{}] destroying [{}]", em.envs[em.cur_env_ind.unwrap_or_default()], em.envs[ind]);
+	drop(em);
 	env_destroy(ind);
 	0
 }
+/// destroy env and all its descendants
+fn sys_env_destroy_tree(envid: EnvID) -> i32 {
+	let em = ENV_MANAGER.borrow_mut();
+	let ind = try_or_return!(em.envid2ind(envid, 1));
+	debug!("[{}] destroying tree of [{}]", em.envs[em.cur_env_ind.unwrap_or_default()], em.envs[ind]);
+	drop(em);
+	env_destroy_tree(ind, true);
+	0
+}
+/// set whether destroying env also destroys its descendants
+fn sys_set_teardown(envid: EnvID, on: usize) -> i32 {
+	let mut em = ENV_MANAGER.borrow_mut();
+	let ind = try_or_return!(em.envid2ind(envid, 1));
+	em.envs[ind].env_teardown = on != 0;
+	0
+}
+/// debug an env, see ptrace module for requests
+fn sys_ptrace(req: usize, envid: EnvID, addr: VirtAddr, buf: VirtAddr, len: usize) -> i32 {
+	match req {
+		PT_ATTACH => {
+			let mut em = ENV_MANAGER.borrow_mut();
+			let ind = try_or_return!(em.envid2ind(envid, 1));
+			try_or_return!(em.trace_attach(ind));
+		},
+		PT_DETACH => {
+			let mut em = ENV_MANAGER.borrow_mut();
+			let ind = try_or_return!(em.tracee(envid));
+			em.trace_detach(ind);
+		},
+		PT_READ => {
+			if len > PT_XFER_MAX || is_illegal_va_range(buf, len) {
+				return Error::Inval.into();
+			}
+			let em = ENV_MANAGER.borrow_mut();
+			let ind = try_or_return!(em.tracee(envid));
+			let data = try_or_return!(em.trace_read(ind, addr, len));
+			drop(em);
+			let dst = unsafe { slice::from_raw_parts_mut(buf.as_mut_ptr::<u8>(), len) };
+			dst.copy_from_slice(&data);
+		},
+		PT_WRITE => {
+			if len > PT_XFER_MAX || is_illegal_va_range(buf, len) {
+				return Error::Inval.into();
+			}
+			let data = unsafe { slice::from_raw_parts(buf.as_ptr::<u8>(), len) }.to_vec();
+			let mut em = ENV_MANAGER.borrow_mut();
+			let ind = try_or_return!(em.tracee(envid));
+			try_or_return!(em.trace_write(ind, addr, &data));
+		},
+		PT_GETREGS => {
+			if is_illegal_va_range(buf, size_of::<Trapframe>()) {
+				return Error::Inval.into();
+			}
+			let em = ENV_MANAGER.borrow_mut();
+			let ind = try_or_return!(em.tracee(envid));
+			let tf = em.envs[ind].env_tf;
+			drop(em);
+			unsafe { write_volatile(buf.as_mut_ptr::<Trapframe>(), tf) };
+		},
+		PT_SETREGS => {
+			if is_illegal_va_range(buf, size_of::<Trapframe>()) {
+				return Error::Inval.into();
+			}
+			let mut tf = unsafe { *buf.as_ptr::<Trapframe>() };
+			let mut em = ENV_MANAGER.borrow_mut();
+			let ind = try_or_return!(em.tracee(envid));
+			tf.cp0_status = em.envs[ind].env_tf.cp0_status;
+			em.envs[ind].env_tf = tf;
+		},
+		PT_CONT | PT_STEP => {
+			let mut em = ENV_MANAGER.borrow_mut();
+			let ind = try_or_return!(em.tracee(envid));
+			if em.envs[ind].env_trace_stop == 0 {
+				return Error::Inval.into();
+			}
+			if req == PT_STEP {
+				try_or_return!(em.plant_step_breaks(ind));
+			}
+			em.trace_resume(ind);
+		},
+		PT_WAIT => return try_or_return!(trace_wait(envid)) as i32,
+		PT_STOP => {
+			let mut em = ENV_MANAGER.borrow_mut();
+			let ind = try_or_return!(em.tracee(envid));
+			if em.envs[ind].env_trace_stop == 0 {
+				em.trace_stop(ind, STOP_REQUEST);
+			}
+		},
+		_ => return Error::Inval.into()
+	}
+	0
+}
+/// get info of the latest core dump
+fn sys_core_info(buf: VirtAddr) -> i32 {
+	if is_illegal_va_range(buf, size_of::<CoreInfo>()) {
+		return Error::Inval.into();
+	}
+	let info = match CORE.borrow_mut().as_ref() {
+		Some(core) => {
+			try_or_return!(ENV_MANAGER.borrow_mut().core_permitted(core));
+			core.info()
+		},
+		None => return Error::NotFound.into()
+	};
+	unsafe { write_volatile(buf.as_mut_ptr::<CoreInfo>(), info) };
+	0
+}
+/// read the latest core dump at offset, return number of bytes read.
+/// only the parent of the crashed env or the core supervisor may read it.
+fn sys_core_read(buf: VirtAddr, offset: usize, len: usize) -> i32 {
+	if len > CORE_XFER_MAX || is_illegal_va_range(buf, len) {
+		return Error::Inval.into();
+	}
+	let mut data = vec![0; len];
+	let n = match CORE.borrow_mut().as_ref() {
+		Some(core) => {
+			try_or_return!(ENV_MANAGER.borrow_mut().core_permitted(core));
+			core.read(offset, &mut data)
+		},
+		None => return Error::NotFound.into()
+	};
+	let dst = unsafe { slice::from_raw_parts_mut(buf.as_mut_ptr::<u8>(), n) };
+	dst.copy_from_slice(&data[..n]);
+	n as i32
+}
+/// drop the latest core dump, only the parent of the crashed env or the core supervisor may
+fn sys_core_clear() -> i32 {
+	let permitted = match CORE.borrow_mut().as_ref() {
+		Some(core) => ENV_MANAGER.borrow_mut().core_permitted(core),
+		None => return Error::NotFound.into()
+	};
+	try_or_return!(permitted);
+	core_release();
+	0
+}
+/// become the core supervisor and wait until a core dump is held.
+/// fails if another live env is the supervisor.
+fn sys_core_wait() -> i32 {
+	try_or_return!(ENV_MANAGER.borrow_mut().core_supervise());
+	if CORE.borrow_mut().is_some() {
+		return 0;
+	}
+	CORE_WAITERS.borrow_mut().sleep();
+	env_sched(1);
+}
+/// set syscall tracing flags of env, return the old flags
+fn sys_strace(envid: EnvID, flags: usize) -> i32 {
+	if flags & !STRACE_MASK != 0 {
+		return Error::Inval.into();
+	}
+	let mut em = ENV_MANAGER.borrow_mut();
+	let ind = try_or_return!(em.envid2ind(envid, 1));
+	let old = em.envs[ind].env_strace;
+	em.envs[ind].env_strace = flags;
+	old as i32
+}
+/// take the oldest syscall records, return number of records read
+fn sys_strace_read(buf: VirtAddr, max: usize) -> i32 {
+	let max = max.min(STRACE_BUF_LEN);
+	if is_illegal_va_range(buf, max * size_of::<SyscallRecord>()) {
+		return Error::Inval.into();
+	}
+	let mut records = Vec::with_capacity(max);
+	records.resize(max, SyscallRecord::default());
+	let n = STRACE.borrow_mut().drain(&mut records);
+	let dst = unsafe { slice::from_raw_parts_mut(buf.as_mut_ptr::<SyscallRecord>(), n) };
+	dst.copy_from_slice(&records[..n]);
+	n as i32
+}
+/// read the newest kernel log text, return number of bytes read
+fn sys_klog_read(buf: VirtAddr, len: usize) -> i32 {
+	let len = len.min(LOG_BUF_LEN);
+	if is_illegal_va_range(buf, len) {
+		return Error::Inval.into();
+	}
+	let mut text = vec![0; len];
+	let n = LOG.borrow_mut().read(&mut text);
+	let dst = unsafe { slice::from_raw_parts_mut(buf.as_mut_ptr::<u8>(), n) };
+	dst.copy_from_slice(&text[..n]);
+	n as i32
+}
+/// start sampling env on timer interrupts, envid zero samples everything
+fn sys_prof_start(envid: EnvID) -> i32 {
+	if envid.as_usize() != 0 {
+		try_or_return!(envid2ind(envid, 0));
+	}
+	PROFILER.borrow_mut().start(envid);
+	0
+}
+/// stop sampling, return number of samples dropped because the buffer was full
+fn sys_prof_stop() -> i32 {
+	PROFILER.borrow_mut().stop() as i32
+}
+/// take the oldest samples, return number of samples read
+fn sys_prof_read(buf: VirtAddr, max: usize) -> i32 {
+	let max = max.min(PROF_BUF_LEN);
+	if is_illegal_va_range(buf, max * size_of::<ProfSample>()) {
+		return Error::Inval.into();
+	}
+	let mut samples = vec![ProfSample::default(); max];
+	let n = PROFILER.borrow_mut().drain(&mut samples);
+	let dst = unsafe { slice::from_raw_parts_mut(buf.as_mut_ptr::<ProfSample>(), n) };
+	dst.copy_from_slice(&samples[..n]);
+	n as i32
+}
+/// read the first max tlb entries, return number of entries read
+fn sys_tlb_read(buf: VirtAddr, max: usize) -> i32 {
+	let max = max.min(tlb_entries());
+	if is_illegal_va_range(buf, max * size_of::<TlbEntry>()) {
+		return Error::Inval.into();
+	}
+	let entries: Vec<TlbEntry> = (0..max).map(tlb_read).collect();
+	let dst = unsafe { slice::from_raw_parts_mut(buf.as_mut_ptr::<TlbEntry>(), max) };
+	dst.copy_from_slice(&entries);
+	max as i32
+}
+/// check frame ref counts against their holders, the result is written to buf unless it is null.
+/// return number of problems found
+fn sys_memcheck(buf: VirtAddr) -> i32 {
+	if buf.as_usize() != 0 && is_illegal_va_range(buf, size_of::<MemCheck>()) {
+		return Error::Inval.into();
+	}
+	let check = memcheck();
+	if buf.as_usize() != 0 {
+		unsafe { write_volatile(buf.as_mut_ptr::<MemCheck>(), check) };
+	}
+	check.problems() as i32
+}
 /// set tlb mod entry of env
 fn sys_set_tlb_mod_entry(envid: EnvID, func: usize) -> i32 {
 	let mut em = ENV_MANAGER.borrow_mut();
@@ -133,6 +435,7 @@ fn sys_mem_alloc(envid: EnvID, va: VirtAddr, perm: usize) -> i32 {
 		return Error::Inval.into();
 	}
 	let ind = try_or_return!(envid2ind(envid, 1));
+	try_or_return!(ENV_MANAGER.borrow_mut().check_frames(ind, va));
 	let ppn = try_or_return!(frame_alloc());
 	let env = &mut ENV_MANAGER.borrow_mut().envs[ind];
 	if let Some(pgdir) = env.env_pgdir.borrow_mut() {
@@ -148,6 +451,7 @@ fn sys_mem_map(srcid: EnvID, srcva: VirtAddr, dstid: EnvID, dstva: VirtAddr, per
 	let mut em = ENV_MANAGER.borrow_mut();
 	let srcind = try_or_return!(em.envid2ind(srcid, 1));
 	let dstind = try_or_return!(em.envid2ind(dstid, 1));
+	try_or_return!(em.check_frames(dstind, dstva));
 	let ppn = if let Some(pgdir) = em.envs[srcind].env_pgdir.borrow_mut() {
 		try_or_return!(pgdir.lookup_ppn(srcva))
 	} else {
@@ -180,14 +484,32 @@ fn sys_exofork() -> i32 {
 	let cur_env = &mut em.envs[cur_env_ind];
 	let cur_env_pri = cur_env.env_pri;
 	let cur_env_id = cur_env.env_id;
+	let cur_env_name = cur_env.env_name;
+	let cur_env_cmdline = cur_env.env_cmdline.clone();
+	let cur_env_pgid = cur_env.env_pgid;
+	let cur_env_sid = cur_env.env_sid;
+	let cur_env_rlimit = cur_env.env_rlimit;
+	let cur_env_shm_attached = cur_env.env_shm_attached;
+	let cur_env_strace = cur_env.env_strace;
+	try_or_return!(em.check_children(cur_env_ind));
 	let envid = try_or_return!(em.alloc(cur_env_id));
 	let env_ind = envid.envx();
 	let env = &mut em.envs[env_ind];
 	env.env_pri = cur_env_pri;
+	env.env_name = cur_env_name;
+	env.env_cmdline = cur_env_cmdline;
+	env.env_pgid = cur_env_pgid;
+	env.env_sid = cur_env_sid;
+	env.env_rlimit = cur_env_rlimit;
+	env.env_shm_attached = cur_env_shm_attached;
+	if cur_env_strace & STRACE_FOLLOW != 0 {
+		env.env_strace = cur_env_strace;
+	}
 
 	env.load_tf((KSTACKTOP - size_of::<Trapframe>()) as *const Trapframe);
 	env.env_tf.regs[2] = 0;
 	env.env_status = EnvStatus::NotRunnable;
+	em.envs.sync_info(env_ind);
 	envid.0 as i32
 }
 /// set env status of env
@@ -206,6 +528,7 @@ fn sys_set_env_status(envid: EnvID, status: EnvStatus) -> i32 {
 	if status == EnvStatus::Runnable {
 		em.env_sched_list.insert_tail(ind);
 	}
+	em.envs.sync_info(ind);
 	0
 }
 /// set trap frame of env
@@ -226,6 +549,124 @@ fn sys_set_trapframe(envid: EnvID, tf: *const Trapframe) -> i32 {
 		return 0;
 	}
 }
+/// get trap frame of env
+fn sys_get_trapframe(envid: EnvID, tf: *mut Trapframe) -> i32 {
+	if is_illegal_va_range(VirtAddr::from_ptr(tf), size_of::<Trapframe>()) {
+		return Error::Inval.into();
+	}
+	let em = ENV_MANAGER.borrow_mut();
+	let ind = try_or_return!(em.envid2ind(envid, 1));
+	let src = if Some(ind) == em.cur_env_ind {
+		unsafe { *((KSTACKTOP - size_of::<Trapframe>()) as *const Trapframe) }
+	} else {
+		em.envs[ind].env_tf
+	};
+	// user page may be missing from tlb, the refill handler needs env manager
+	drop(em);
+	unsafe { write_volatile(tf, src) };
+	0
+}
+/// list ids of envs in use, return the number written
+fn sys_env_list(buf: VirtAddr, max: usize) -> i32 {
+	let max = max.min(NENV);
+	if is_illegal_va_range(buf, max * size_of::<u32>()) {
+		return Error::Inval.into();
+	}
+	let em = ENV_MANAGER.borrow_mut();
+	let mut ids = Vec::new();
+	for ind in 0..em.envs.len() {
+		if ids.len() == max {
+			break;
+		}
+		let env = &em.envs[ind];
+		if env.env_status != EnvStatus::Free {
+			ids.push(env.env_id.as_usize() as u32);
+		}
+	}
+	drop(em);
+	let dst = unsafe { slice::from_raw_parts_mut(buf.as_mut_ptr::<u32>(), ids.len()) };
+	dst.copy_from_slice(&ids);
+	ids.len() as i32
+}
+/// get a snapshot of env
+fn sys_env_stat(envid: EnvID, stat: VirtAddr) -> i32 {
+	if is_illegal_va_range(stat, size_of::<EnvStat>()) {
+		return Error::Inval.into();
+	}
+	let mut em = ENV_MANAGER.borrow_mut();
+	let ind = try_or_return!(em.envid2ind(envid, 0));
+	em.charge(true);
+	let st = EnvStat::from(&em.envs[ind]);
+	drop(em);
+	unsafe { write_volatile(stat.as_mut_ptr::<EnvStat>(), st) };
+	0
+}
+/// get resource usage of current env or its freed children
+fn sys_getrusage(who: usize, buf: VirtAddr) -> i32 {
+	if is_illegal_va_range(buf, size_of::<RusageInfo>()) {
+		return Error::Inval.into();
+	}
+	let mut em = ENV_MANAGER.borrow_mut();
+	let cur_ind = em.cur_env_ind.unwrap_or_default();
+	em.charge(true);
+	em.sample_rss(cur_ind);
+	let env = &em.envs[cur_ind];
+	let info = match who {
+		RUSAGE_SELF => RusageInfo::from(&env.env_rusage),
+		RUSAGE_CHILDREN => RusageInfo::from(&env.env_rusage_children),
+		_ => return Error::Inval.into()
+	};
+	drop(em);
+	unsafe { write_volatile(buf.as_mut_ptr::<RusageInfo>(), info) };
+	0
+}
+/// get resource limit of env
+fn sys_get_rlimit(envid: EnvID, res: usize) -> i32 {
+	let res = try_or_return!(Resource::try_from(res));
+	let em = ENV_MANAGER.borrow_mut();
+	let ind = try_or_return!(em.envid2ind(envid, 0));
+	em.envs[ind].env_rlimit.get(res) as i32
+}
+/// set resource limit of env, only the parent may raise a limit and never above its own
+fn sys_set_rlimit(envid: EnvID, res: usize, value: usize) -> i32 {
+	let res = try_or_return!(Resource::try_from(res));
+	let mut em = ENV_MANAGER.borrow_mut();
+	let ind = try_or_return!(em.envid2ind(envid, 1));
+	let cur_env = &em.envs[em.cur_env_ind.unwrap_or_default()];
+	let (cur_env_id, cur_limit) = (cur_env.env_id, cur_env.env_rlimit.get(res));
+	let env = &mut em.envs[ind];
+	if env.env_parent_id != cur_env_id && value > env.env_rlimit.get(res) {
+		return Error::Inval.into();
+	}
+	env.env_rlimit.set(res, value.min(cur_limit));
+	0
+}
+/// set command line of env, its name is taken from the first argument
+fn sys_set_cmdline(envid: EnvID, buf: VirtAddr, len: usize) -> i32 {
+	let len = len.min(ENV_CMDLINE_MAX);
+	if is_illegal_va_range(buf, len) {
+		return Error::Inval.into();
+	}
+	let cmdline = unsafe { slice::from_raw_parts(buf.as_ptr::<u8>(), len) }.to_vec();
+	let mut em = ENV_MANAGER.borrow_mut();
+	let ind = try_or_return!(em.envid2ind(envid, 1));
+	em.envs[ind].set_cmdline(&cmdline);
+	0
+}
+/// get command line of env, return its length
+fn sys_get_cmdline(envid: EnvID, buf: VirtAddr, len: usize) -> i32 {
+	if is_illegal_va_range(buf, len) {
+		return Error::Inval.into();
+	}
+	let em = ENV_MANAGER.borrow_mut();
+	let ind = try_or_return!(em.envid2ind(envid, 0));
+	let cmdline = em.envs[ind].env_cmdline.clone();
+	drop(em);
+	let n = cmdline.len().min(len);
+	let dst = unsafe { slice::from_raw_parts_mut(buf.as_mut_ptr::<u8>(), n) };
+	dst.copy_from_slice(&cmdline[..n]);
+	n as i32
+}
 /// panic
 fn sys_panic(msg: *const i8) {
 	let s = unsafe {CStr::from_ptr(msg)};
@@ -243,6 +684,8 @@ fn sys_ipc_recv(dstva: VirtAddr) -> i32 {
 	env.env_ipc_receiving = 1;
 	env.env_ipc_dstva = dstva;
 	env.env_status = EnvStatus::NotRunnable;
+	env.env_rusage.ipc_recvs += 1;
+	em.envs.sync_info(cur_ind);
 
 	em.env_sched_list.remove(cur_ind);
 	let tf = (KSTACKTOP - size_of::<Trapframe>()) as *mut Trapframe;
@@ -267,17 +710,22 @@ fn sys_ipc_try_send(envid: EnvID, value: usize, srcva: VirtAddr, perm: usize) ->
 	if recving == 0 {
 		return Error::IpcNotRecv.into();
 	}
+	if !srcva.is_null() {
+		let dstva = env.env_ipc_dstva;
+		try_or_return!(em.check_frames(ind, dstva));
+	}
+	let env = &mut em.envs[ind];
 
 	env.env_ipc_value = value;
 	env.env_ipc_from = cur_env_id.0;
 	env.env_ipc_perm = PTE_V | perm;
 	env.env_ipc_receiving = 0;
-	env.env_status = EnvStatus::Runnable;
 	let dstva = env.env_ipc_dstva;
 	let asid = env.env_asid;
+	em.envs.sync_info(ind);
+	em.envs[cur_ind].env_rusage.ipc_sends += 1;
 
-
-	em.env_sched_list.insert_tail(ind);
+	em.unblock(ind);
 	if !srcva.is_null() {
 		let cur_env = &mut em.envs[cur_ind];
 		let ppn = if let Some(pgdir) = cur_env.env_pgdir.borrow_mut() {
@@ -346,13 +794,20 @@ fn sys_read_dev(va: VirtAddr, pa: PhysAddr, len: usize) -> i32 {
 fn sys_shmget(key: usize, size: usize) -> i32 {
 	try_or_return!(shm_get(key, size))
 }
-/// map shared memory at va
+/// map shared memory at va, the shm limit counts distinct segments so attaching one again is not charged
 fn sys_shmat(id: usize, va: VirtAddr, perm: usize) -> i32 {
+	if id >= SHMMNI {
+		return Error::Inval.into();
+	}
 	let mut em = ENV_MANAGER.borrow_mut();
 	let ind = em.cur_env_ind.unwrap();
 	let env = &mut em.envs[ind];
+	if env.env_shm_attached[id] == 0 {
+		try_or_return!(env.env_rlimit.check(Resource::Shm, env.shm_segments(), Error::NoMem));
+	}
 	if let Some(pgdir) = &mut env.env_pgdir {
-		try_or_return!(shm_at(id, va, env.env_asid, pgdir, perm))
+		try_or_return!(shm_at(id, va, env.env_asid, pgdir, perm));
+		env.env_shm_attached[id] = env.env_shm_attached[id].saturating_add(1);
 	}
 	0
 }
@@ -362,7 +817,8 @@ fn sys_shmdt(id: usize, va: VirtAddr) -> i32 {
 	let ind = em.cur_env_ind.unwrap();
 	let env = &mut em.envs[ind];
 	if let Some(pgdir) = &mut env.env_pgdir {
-		try_or_return!(shm_dt(id, va, env.env_asid, pgdir))
+		try_or_return!(shm_dt(id, va, env.env_asid, pgdir));
+		env.env_shm_attached[id] = env.env_shm_attached[id].saturating_sub(1);
 	}
 	0
 }
@@ -403,6 +859,126 @@ fn sys_semkill(id: usize) {
 	let mut sem_manager = SEM_MAMANER.borrow_mut();
 	sem_manager.sem_free(id);
 }
+/// max bytes returned by one console read
+const CONSOLE_READ_MAX: usize = 128;
+/// read from console tty, block until some input arrives
+fn sys_console_read(buf: VirtAddr, len: usize) -> i32 {
+	if is_illegal_va_range(buf, len) {
+		return Error::Inval.into();
+	}
+	if len == 0 {
+		return 0;
+	}
+	let mut kbuf = [0u8; CONSOLE_READ_MAX];
+	let len = len.min(CONSOLE_READ_MAX);
+	let mut tty = TTY.borrow_mut();
+	let n = match tty.read(&mut kbuf[..len]) {
+		Some(n) => n,
+		None => {
+			tty.readers.sleep();
+			drop(tty);
+			env_sched(1);
+		}
+	};
+	drop(tty);
+	let dst = unsafe { slice::from_raw_parts_mut(buf.as_mut_ptr::<u8>(), n) };
+	dst.copy_from_slice(&kbuf[..n]);
+	n as i32
+}
+/// control console tty. the foreground may only be set to a process group current env may notify,
+/// or cleared with 0
+fn sys_tty_ioctl(cmd: usize, arg: usize) -> i32 {
+	if cmd == TtyCmd::SetForeground as usize && arg != 0 {
+		try_or_return!(ENV_MANAGER.borrow_mut().check_group(EnvID::new(arg)));
+	}
+	try_or_return!(TTY.borrow_mut().ioctl(cmd, arg)) as i32
+}
+/// send a notification to env
+fn sys_env_notify(envid: EnvID, note: usize) -> i32 {
+	let note = match Note::from_usize(note) {
+		Some(note) => note,
+		None => return Error::Inval.into()
+	};
+	let ind = try_or_return!(envid2ind(envid, 1));
+	env_notify(ind, note);
+	0
+}
+/// move env to a process group of its session
+fn sys_set_pgid(envid: EnvID, pgid: EnvID) -> i32 {
+	let mut em = ENV_MANAGER.borrow_mut();
+	let ind = try_or_return!(em.envid2ind(envid, 1));
+	try_or_return!(em.set_pgid(ind, pgid));
+	0
+}
+/// get process group of env
+fn sys_get_pgid(envid: EnvID) -> i32 {
+	let em = ENV_MANAGER.borrow_mut();
+	let ind = try_or_return!(em.envid2ind(envid, 0));
+	em.envs[ind].env_pgid.as_usize() as i32
+}
+/// start a new session led by current env
+fn sys_set_sid() -> i32 {
+	let mut em = ENV_MANAGER.borrow_mut();
+	let ind = em.cur_env_ind.unwrap_or_default();
+	try_or_return!(em.set_sid(ind)).as_usize() as i32
+}
+/// get session of env
+fn sys_get_sid(envid: EnvID) -> i32 {
+	let em = ENV_MANAGER.borrow_mut();
+	let ind = try_or_return!(em.envid2ind(envid, 0));
+	em.envs[ind].env_sid.as_usize() as i32
+}
+/// send a notification to every env of a process group in the session of current env,
+/// or of a group led by a child of current env
+fn sys_group_notify(pgid: EnvID, note: usize) -> i32 {
+	let note = match Note::from_usize(note) {
+		Some(note) => note,
+		None => return Error::Inval.into()
+	};
+	try_or_return!(ENV_MANAGER.borrow_mut().check_group(pgid));
+	try_or_return!(env_group_notify(pgid, note));
+	0
+}
+/// destroy every env of a process group in the session of current env,
+/// or of a group led by a child of current env
+fn sys_group_destroy(pgid: EnvID) -> i32 {
+	try_or_return!(ENV_MANAGER.borrow_mut().check_group(pgid));
+	try_or_return!(env_group_destroy(pgid));
+	0
+}
+/// set notify entry of env
+fn sys_set_notify_entry(envid: EnvID, func: usize) -> i32 {
+	let mut em = ENV_MANAGER.borrow_mut();
+	let ind = try_or_return!(em.envid2ind(envid, 1));
+	em.envs[ind].env_notify_entry = func;
+	0
+}
+/// get wall-clock time
+fn sys_time_of_day(tv: VirtAddr) -> i32 {
+	if is_illegal_va_range(tv, size_of::<TimeVal>()) {
+		return Error::Inval.into();
+	}
+	let now = time_of_day();
+	unsafe { *tv.as_mut_ptr::<TimeVal>() = now; }
+	0
+}
+/// set wall-clock time, only an env created by the kernel may
+fn sys_set_time_of_day(tv: VirtAddr) -> i32 {
+	if is_illegal_va_range(tv, size_of::<TimeVal>()) {
+		return Error::Inval.into();
+	}
+	let em = ENV_MANAGER.borrow_mut();
+	if !em.envs[em.cur_env_ind.unwrap_or_default()].env_privileged {
+		return Error::BadEnv.into();
+	}
+	drop(em);
+	let tv = unsafe { *tv.as_ptr::<TimeVal>() };
+	if tv.usec as u64 >= 1_000_000 {
+		return Error::Inval.into();
+	}
+	RTC.borrow_mut().set_time_of_day(tv);
+	0
+}
 
 /// get syscall func address from syscall id
 #[inline]
@@ -434,6 +1010,41 @@ fn get_syscall(id: SyscallID) -> usize {
 		SyscallID::SemWait => sys_semwait as usize,
 		SyscallID::SemPost => sys_sempost as usize,
 		SyscallID::SemKill => sys_semkill as usize,
+		SyscallID::ConsoleRead => sys_console_read as usize,
+		SyscallID::TtyIoctl => sys_tty_ioctl as usize,
+		SyscallID::EnvNotify => sys_env_notify as usize,
+		SyscallID::SetNotifyEntry => sys_set_notify_entry as usize,
+		SyscallID::TimeOfDay => sys_time_of_day as usize,
+		SyscallID::SetTimeOfDay => sys_set_time_of_day as usize,
+		SyscallID::GetTrapframe => sys_get_trapframe as usize,
+		SyscallID::EnvList => sys_env_list as usize,
+		SyscallID::EnvStat => sys_env_stat as usize,
+		SyscallID::GetRusage => sys_getrusage as usize,
+		SyscallID::GetRlimit => sys_get_rlimit as usize,
+		SyscallID::SetRlimit => sys_set_rlimit as usize,
+		SyscallID::SetCmdline => sys_set_cmdline as usize,
+		SyscallID::GetCmdline => sys_get_cmdline as usize,
+		SyscallID::SetPgid => sys_set_pgid as usize,
+		SyscallID::GetPgid => sys_get_pgid as usize,
+		SyscallID::SetSid => sys_set_sid as usize,
+		SyscallID::GetSid => sys_get_sid as usize,
+		SyscallID::GroupNotify => sys_group_notify as usize,
+		SyscallID::GroupDestroy => sys_group_destroy as usize,
+		SyscallID::EnvDestroyTree => sys_env_destroy_tree as usize,
+		SyscallID::SetTeardown => sys_set_teardown as usize,
+		SyscallID::Ptrace => sys_ptrace as usize,
+		SyscallID::CoreInfo => sys_core_info as usize,
+		SyscallID::CoreRead => sys_core_read as usize,
+		SyscallID::CoreClear => sys_core_clear as usize,
+		SyscallID::Strace => sys_strace as usize,
+		SyscallID::StraceRead => sys_strace_read as usize,
+		SyscallID::KlogRead => sys_klog_read as usize,
+		SyscallID::ProfStart => sys_prof_start as usize,
+		SyscallID::ProfStop => sys_prof_stop as usize,
+		SyscallID::ProfRead => sys_prof_read as usize,
+		SyscallID::TlbRead => sys_tlb_read as usize,
+		SyscallID::MemCheck => sys_memcheck as usize,
+		SyscallID::CoreWait => sys_core_wait as usize,
 		SyscallID::SysNo => panic!("No such syscall"),
 	}
 }
@@ -464,11 +1075,18 @@ impl Syscall {
 	
 		let ret = func(arg1, arg2, arg3, arg4, arg5);
 		tf.regs[2] = ret as usize;
+		strace_syscall(sysno, [arg1, arg2, arg3, arg4, arg5], ret);
 	}
 }
 
 /// do syscall
 #[no_mangle]
 pub extern "C" fn do_syscall(tf: &mut Trapframe) {
+	env_enter_kernel();
+	let sysno = tf.regs[4];
+	if sysno < NSYSCALL {
+		cur_rusage(|ru| ru.syscalls[sysno] += 1);
+	}
 	Syscall.do_syscall(tf);
+	env_leave_kernel();
 }
\ No newline at end of file
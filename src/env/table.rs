@@ -0,0 +1,82 @@
+use core::{mem::size_of, ops::{Index, IndexMut}, slice};
+
+use alloc::{boxed::Box, vec::Vec};
+
+use crate::{err::Error, memory::{frame::{frame_alloc, frame_incref}, mmu::{PhysAddr, PhysPageNum, VirtAddr, PAGE_SIZE}}};
+
+use super::{info::EnvInfo, Env};
+
+/// envs in a chunk, their info records fill one page
+pub const ENV_CHUNK: usize = PAGE_SIZE / size_of::<EnvInfo>();
+
+/// env table growing by chunks, an env never moves once allocated.
+/// each chunk has a page of info records to be mapped for user space.
+pub struct EnvTable<'a> {
+    chunks: Vec<&'a mut [Env<'a>]>,
+    infos: Vec<&'a mut [EnvInfo]>,
+}
+
+impl<'a> EnvTable<'a> {
+    /// create an empty table
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            chunks: Vec::new(),
+            infos: Vec::new()
+        }
+    }
+
+    /// number of envs
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.chunks.len() * ENV_CHUNK
+    }
+
+    /// add a chunk of free envs, return the page of its info records
+    pub fn grow(&mut self) -> Result<PhysPageNum, Error> {
+        let ppn = frame_alloc()?;
+        frame_incref(ppn);
+        let infos = unsafe { slice::from_raw_parts_mut(ppn.into_kva().as_mut_ptr::<EnvInfo>(), ENV_CHUNK) };
+        infos.fill(EnvInfo::new());
+        let chunk: Vec<Env<'a>> = (0..ENV_CHUNK).map(|_| Env::new()).collect();
+        self.chunks.push(Box::leak(chunk.into_boxed_slice()));
+        self.infos.push(infos);
+        Ok(ppn)
+    }
+
+    /// pages of info records, one per chunk
+    pub fn info_pages(&self) -> Vec<PhysPageNum> {
+        self.infos.iter()
+            .map(|infos| PhysPageNum::from(PhysAddr::from_kva(VirtAddr::from_ptr(infos.as_ptr()))))
+            .collect()
+    }
+
+    /// info record of env
+    #[inline]
+    pub fn info(&self, ind: usize) -> &EnvInfo {
+        &self.infos[ind / ENV_CHUNK][ind % ENV_CHUNK]
+    }
+
+    /// publish user visible fields of env to its info record
+    #[inline]
+    pub fn sync_info(&mut self, ind: usize) {
+        let info = EnvInfo::from(&self[ind]);
+        self.infos[ind / ENV_CHUNK][ind % ENV_CHUNK] = info;
+    }
+}
+
+impl<'a> Index<usize> for EnvTable<'a> {
+    type Output = Env<'a>;
+
+    #[inline]
+    fn index(&self, ind: usize) -> &Self::Output {
+        &self.chunks[ind / ENV_CHUNK][ind % ENV_CHUNK]
+    }
+}
+
+impl<'a> IndexMut<usize> for EnvTable<'a> {
+    #[inline]
+    fn index_mut(&mut self, ind: usize) -> &mut Self::Output {
+        &mut self.chunks[ind / ENV_CHUNK][ind % ENV_CHUNK]
+    }
+}
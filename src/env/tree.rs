@@ -0,0 +1,67 @@
+use alloc::{vec, vec::Vec};
+
+use super::{EnvID, EnvManager};
+
+impl<'a> EnvManager<'a> {
+    /// add env to the child list of parent
+    pub fn link_child(&mut self, parent: usize, ind: usize) {
+        let head = self.envs[parent].env_first_child;
+        if let Some(head) = head {
+            self.envs[head].env_prev_sibling = Some(ind);
+        }
+        let env = &mut self.envs[ind];
+        env.env_prev_sibling = None;
+        env.env_next_sibling = head;
+        self.envs[parent].env_first_child = Some(ind);
+    }
+
+    /// remove env from the child list of parent
+    pub fn unlink_child(&mut self, parent: usize, ind: usize) {
+        let prev = self.envs[ind].env_prev_sibling;
+        let next = self.envs[ind].env_next_sibling;
+        match prev {
+            Some(prev) => self.envs[prev].env_next_sibling = next,
+            None => self.envs[parent].env_first_child = next
+        }
+        if let Some(next) = next {
+            self.envs[next].env_prev_sibling = prev;
+        }
+        let env = &mut self.envs[ind];
+        env.env_prev_sibling = None;
+        env.env_next_sibling = None;
+    }
+
+    /// children of env
+    pub fn children(&self, ind: usize) -> Vec<usize> {
+        let mut children = Vec::new();
+        let mut child = self.envs[ind].env_first_child;
+        while let Some(c) = child {
+            children.push(c);
+            child = self.envs[c].env_next_sibling;
+        }
+        children
+    }
+
+    /// descendants of env, every env comes before its parent
+    pub fn descendants(&self, ind: usize) -> Vec<usize> {
+        let mut found = Vec::new();
+        let mut stack = vec![ind];
+        while let Some(i) = stack.pop() {
+            for c in self.children(i) {
+                found.push(c);
+                stack.push(c);
+            }
+        }
+        found.reverse();
+        found
+    }
+
+    /// detach children of env, they become orphans with no parent
+    pub fn orphan_children(&mut self, ind: usize) {
+        for c in self.children(ind) {
+            self.unlink_child(ind, c);
+            self.envs[c].env_parent_id = EnvID::zero();
+            self.envs.sync_info(c);
+        }
+    }
+}
@@ -23,4 +23,29 @@ impl Into<i32> for Error {
     fn into(self) -> i32 {
         -(self as i32)
     }
-}
\ No newline at end of file
+}
+
+impl TryFrom<i32> for Error {
+    type Error = ();
+    /// decode a negative syscall return value
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        Ok(match -value {
+            1 => Error::Unspecified,
+            2 => Error::BadEnv,
+            3 => Error::Inval,
+            4 => Error::NoMem,
+            5 => Error::NoSys,
+            6 => Error::NoFreeEnv,
+            7 => Error::IpcNotRecv,
+            8 => Error::NoDisk,
+            9 => Error::MaxOpen,
+            10 => Error::NotFound,
+            11 => Error::BadPath,
+            12 => Error::FileExists,
+            13 => Error::NotExec,
+            14 => Error::NotMapped,
+            15 => Error::NoSpc,
+            _ => return Err(())
+        })
+    }
+}
@@ -1,2 +1,4 @@
 /// traps module
-pub mod traps;
\ No newline at end of file
+pub mod traps;
+/// interrupt dispatch
+pub mod irq;
\ No newline at end of file
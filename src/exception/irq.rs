@@ -0,0 +1,86 @@
+use crate::{device::{i8259, malta::*}, env::{env_enter_kernel, env_leave_idle, env_leave_kernel, profile::profile_sample}, err::Error, sync::cell::UPSafeCell, warn};
+
+use super::traps::Trapframe;
+
+/// number of cpu interrupt lines
+pub const CPU_NR_IRQS: usize = 8;
+/// first irq number of i8259 lines
+pub const PIC_IRQ_BASE: usize = CPU_NR_IRQS;
+/// number of irqs
+pub const NR_IRQS: usize = PIC_IRQ_BASE + MALTA_I8259_NR_IRQS;
+/// irq number of cp0 timer
+pub const TIMER_IRQ: usize = MALTA_CPU_TIMER_IRQ;
+
+/// irq number of an i8259 line
+#[inline]
+pub const fn pic_irq(line: usize) -> usize {
+    PIC_IRQ_BASE + line
+}
+
+/// irq handler
+pub type IrqHandler = fn();
+
+/// registered irq handlers
+static IRQ_HANDLERS: UPSafeCell<[Option<IrqHandler>; NR_IRQS]> = UPSafeCell::new([None; NR_IRQS]);
+
+/// register a handler for irq, i8259 lines are unmasked
+pub fn register_irq(irq: usize, handler: IrqHandler) -> Result<(), Error> {
+    if irq >= NR_IRQS || irq == MALTA_CPU_PIC_IRQ {
+        return Err(Error::Inval);
+    }
+    let mut handlers = IRQ_HANDLERS.borrow_mut();
+    if handlers[irq].is_some() {
+        return Err(Error::Inval);
+    }
+    handlers[irq] = Some(handler);
+    drop(handlers);
+    if irq >= PIC_IRQ_BASE {
+        i8259::enable(irq - PIC_IRQ_BASE);
+    }
+    Ok(())
+}
+
+/// call the handler of irq, the table is released first since a handler may not return
+fn handle_irq(irq: usize) {
+    let handler = IRQ_HANDLERS.borrow_mut()[irq];
+    match handler {
+        Some(handler) => handler(),
+        None => {
+            warn!("unhandled irq {}", irq);
+            if irq >= PIC_IRQ_BASE {
+                i8259::disable(irq - PIC_IRQ_BASE);
+            }
+        }
+    }
+}
+
+/// dispatch pending i8259 irq, eoi is sent before the handler runs
+fn do_pic_irq() {
+    if let Some(line) = i8259::pending() {
+        handle_irq(pic_irq(line));
+    }
+}
+
+/// decode pending interrupts from cause and dispatch them, timer goes last.
+/// a timer interrupt is sampled by the profiler first, since its handler may not return.
+/// an irq taken while idle schedules the env it made runnable.
+#[no_mangle]
+pub extern "C" fn do_irq(tf: &mut Trapframe) {
+    env_enter_kernel();
+    let pending = (tf.cp0_cause & tf.cp0_status) >> 8 & 0xff;
+    if pending & (1 << TIMER_IRQ) != 0 {
+        profile_sample(tf);
+    }
+    for irq in 0..CPU_NR_IRQS {
+        if pending & (1 << irq) == 0 {
+            continue;
+        }
+        if irq == MALTA_CPU_PIC_IRQ {
+            do_pic_irq();
+        } else {
+            handle_irq(irq);
+        }
+    }
+    env_leave_kernel();
+    env_leave_idle();
+}
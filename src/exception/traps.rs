@@ -1,12 +1,13 @@
 use core::mem::size_of;
 
-use crate::{env::user_tlb_mod_entry, memory::mmu::{VirtAddr, USTACKTOP, UXSTACKTOP}};
+use crate::{env::{coredump::env_crash, env_enter_kernel, env_leave_kernel, get_cur_env_id, user_tlb_mod_entry}, gdb::{exc_signal, gdb_attached, gdb_trap, SIGSEGV}, memory::mmu::{VirtAddr, USTACKTOP, UXSTACKTOP}};
 
 extern "C" {
     fn handle_int();
     fn handle_tlb();
     fn handle_sys();
     fn handle_mod();
+    fn handle_bp();
     fn handle_reserved();
 }
 /// register constant
@@ -55,6 +56,7 @@ static exception_handlers: [unsafe extern "C" fn(); 32] = {
     template[3] = handle_tlb;
     template[1] = handle_mod;
     template[8] = handle_sys;
+    template[9] = handle_bp;
     template
 };
 
@@ -84,9 +86,14 @@ impl Trapframe {
             cp0_epc: 0
         }
     }
-    /// do tlb mod
+    /// do tlb mod, the env crashes if it has no handler
     #[inline]
     pub fn do_tlb_mod(&mut self) {
+        let mod_entry = user_tlb_mod_entry();
+        if mod_entry == 0 {
+            env_crash(SIGSEGV);
+            return;
+        }
         let tmp_tf = self.clone();
         let sp = VirtAddr::new(self.regs[29]);
         if sp < USTACKTOP || sp >= UXSTACKTOP {
@@ -98,19 +105,25 @@ impl Trapframe {
         let t = unsafe {sp.as_mut()}.unwrap();
         *t = tmp_tf;
 
-        let mod_entry = user_tlb_mod_entry();
-        if mod_entry != 0 {
-            self.regs[4] = self.regs[29];
-            self.regs[29] -= 4;
-            self.cp0_epc = mod_entry;
-        } else {
-            panic!("TLB Mod but no user handler registered");
-        }
+        self.regs[4] = self.regs[29];
+        self.regs[29] -= 4;
+        self.cp0_epc = mod_entry;
     }
 }
 
-/// do reserved exception handler
+/// do reserved exception handler.
+/// faults of user envs are reported to gdb if it is attached, otherwise the env crashes.
 #[no_mangle]
-pub extern "C" fn do_reserved() {
-    panic!("do reserved");
+pub extern "C" fn do_reserved(tf: &mut Trapframe) {
+    let exc_code = (tf.cp0_cause >> 2) & 0x1f;
+    if tf.cp0_status & STATUS_UM == 0 || get_cur_env_id().is_none() {
+        panic!("do reserved, exception code {}", exc_code);
+    }
+    env_enter_kernel();
+    if gdb_attached() {
+        gdb_trap(exc_signal(exc_code));
+    } else {
+        env_crash(exc_signal(exc_code));
+    }
+    env_leave_kernel();
 }
\ No newline at end of file
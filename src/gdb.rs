@@ -0,0 +1,459 @@
+use alloc::{format, string::String, vec::Vec};
+use core::{fmt::Write, ptr::{read_volatile, write_volatile}};
+
+use crate::{device::{ioread8, iowrite8, malta::*}, env::{env_destroy, env_list, env_read, env_stat, env_trapframe, env_write, get_cur_env_id, get_cur_env_ind, EnvID}, err::Error, exception::{irq::{pic_irq, register_irq}, traps::Trapframe}, info, memory::{frame::frame_stats, mmu::{VirtAddr, KSEG0, PGSHIFT, ULIM}}, sync::cell::UPSafeCell};
+
+/// stop signal for an interrupt from gdb
+pub const SIGINT: usize = 2;
+/// stop signal for a reserved instruction
+pub const SIGILL: usize = 4;
+/// stop signal for a break instruction
+pub const SIGTRAP: usize = 5;
+/// stop signal for an arithmetic overflow
+pub const SIGFPE: usize = 8;
+/// stop signal for an address or bus error
+pub const SIGBUS: usize = 10;
+/// stop signal for other faults
+pub const SIGSEGV: usize = 11;
+
+/// registers in a `g` packet: gprs, sr, lo, hi, badvaddr, cause, pc
+const NREGS: usize = 38;
+/// largest packet accepted, in bytes
+const PACKET_MAX: usize = 4096;
+/// ctrl-c sent by gdb to interrupt the target
+const INTERRUPT: u8 = 0x03;
+
+/// global gdb stub
+pub static GDB: UPSafeCell<GdbStub> = UPSafeCell::new(GdbStub::new());
+
+/// init second serial port and wait for gdb on it
+pub fn gdb_init() {
+    GDB.borrow_mut().init();
+    register_irq(pic_irq(MALTA_COM2_IRQ), gdb_intr).unwrap();
+}
+
+/// check if gdb is connected
+#[inline]
+pub fn gdb_attached() -> bool {
+    GDB.borrow_mut().attached
+}
+
+/// report an exception of current env to gdb and serve it until it continues
+pub fn gdb_trap(signal: usize) {
+    let resume = GDB.borrow_mut().serve(signal);
+    resume.apply();
+}
+
+/// second serial irq handler, stop the world when gdb sends a packet or ctrl-c
+pub fn gdb_intr() {
+    let mut gdb = GDB.borrow_mut();
+    if !gdb.poll() {
+        return;
+    }
+    let resume = gdb.serve(SIGINT);
+    drop(gdb);
+    resume.apply();
+}
+
+/// signal reported for an exception code
+pub fn exc_signal(exc_code: usize) -> usize {
+    match exc_code {
+        4..=7 => SIGBUS,
+        9 | 13 => SIGTRAP,
+        10 | 11 => SIGILL,
+        12 => SIGFPE,
+        _ => SIGSEGV
+    }
+}
+
+/// what to do when the stub returns
+enum Resume {
+    Continue,
+    Kill
+}
+
+impl Resume {
+    /// continue current env or destroy it
+    fn apply(self) {
+        if let Resume::Kill = self {
+            if let Some(ind) = get_cur_env_ind() {
+                env_destroy(ind);
+            }
+        }
+    }
+}
+
+/// gdb remote serial protocol stub, envs are reported as threads
+pub struct GdbStub {
+    attached: bool,
+    pushback: Option<u8>,
+    thread: Option<EnvID>
+}
+
+impl GdbStub {
+    /// create a detached stub
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            attached: false,
+            pushback: None,
+            thread: None
+        }
+    }
+
+    /// init the uart with receive interrupt, transmit is polled
+    fn init(&mut self) {
+        iowrite8(MALTA_COM2_IER, 0);
+        iowrite8(MALTA_COM2_FCR, MALTA_SERIAL_FCR_ENABLE | MALTA_SERIAL_FCR_CLEAR);
+        iowrite8(MALTA_COM2_MCR, MALTA_SERIAL_MCR_DTR | MALTA_SERIAL_MCR_RTS | MALTA_SERIAL_MCR_OUT2);
+        iowrite8(MALTA_COM2_IER, MALTA_SERIAL_IER_RDI);
+    }
+
+    /// drain received bytes, return if gdb wants the target to stop
+    fn poll(&mut self) -> bool {
+        let mut stop = false;
+        while ioread8(MALTA_COM2_LSR) & MALTA_SERIAL_DATA_READY != 0 {
+            match ioread8(MALTA_COM2_DATA) {
+                INTERRUPT => stop = true,
+                b'$' => {
+                    self.pushback = Some(b'$');
+                    return true;
+                },
+                _ => {}
+            }
+        }
+        stop
+    }
+
+    /// wait for a byte
+    fn getc(&mut self) -> u8 {
+        if let Some(byte) = self.pushback.take() {
+            return byte;
+        }
+        while ioread8(MALTA_COM2_LSR) & MALTA_SERIAL_DATA_READY == 0 {}
+        ioread8(MALTA_COM2_DATA)
+    }
+
+    /// send a byte
+    fn putc(&mut self, byte: u8) {
+        while ioread8(MALTA_COM2_LSR) & MALTA_SERIAL_THR_EMPTY == 0 {}
+        iowrite8(MALTA_COM2_DATA, byte);
+    }
+
+    /// receive a packet with valid checksum and acknowledge it
+    fn recv_packet(&mut self) -> Vec<u8> {
+        loop {
+            while self.getc() != b'$' {}
+            let mut data = Vec::new();
+            let mut sum = 0u8;
+            loop {
+                let byte = self.getc();
+                if byte == b'#' {
+                    break;
+                }
+                sum = sum.wrapping_add(byte);
+                if data.len() < PACKET_MAX {
+                    data.push(byte);
+                }
+            }
+            let hi = hex_value(self.getc());
+            let lo = hex_value(self.getc());
+            match (hi, lo) {
+                (Some(hi), Some(lo)) if (hi << 4 | lo) as u8 == sum => {
+                    self.putc(b'+');
+                    return data;
+                },
+                _ => self.putc(b'-')
+            }
+        }
+    }
+
+    /// send a packet until gdb acknowledges it
+    fn send_packet(&mut self, data: &str) {
+        let sum = data.bytes().fold(0u8, |sum, b| sum.wrapping_add(b));
+        loop {
+            self.putc(b'$');
+            for byte in data.bytes() {
+                self.putc(byte);
+            }
+            self.putc(b'#');
+            for byte in format!("{:02x}", sum).bytes() {
+                self.putc(byte);
+            }
+            match self.getc() {
+                b'+' => return,
+                b'$' => {
+                    self.pushback = Some(b'$');
+                    return;
+                },
+                _ => {}
+            }
+        }
+    }
+
+    /// stop reply naming current env as the stopped thread
+    fn stop_reply(&self, signal: usize) -> String {
+        match get_cur_env_id() {
+            Some(id) => format!("T{:02x}thread:{:x};", signal, id),
+            None => format!("S{:02x}", signal)
+        }
+    }
+
+    /// serve gdb until it continues, detaches or kills current env
+    fn serve(&mut self, signal: usize) -> Resume {
+        if self.attached {
+            let reply = self.stop_reply(signal);
+            self.send_packet(&reply);
+        } else {
+            info!("gdb: attached");
+            self.attached = true;
+        }
+        self.thread = get_cur_env_id();
+        loop {
+            let packet = self.recv_packet();
+            let (cmd, args) = match packet.split_first() {
+                Some((cmd, args)) => (*cmd, core::str::from_utf8(args).unwrap_or("")),
+                None => continue
+            };
+            let reply = match cmd {
+                b'?' => self.stop_reply(signal),
+                b'q' => self.query(args),
+                b'H' => match args.get(1..).map(parse_thread) {
+                    Some(thread) => {
+                        match thread {
+                            Some(Some(id)) => self.thread = Some(id),
+                            Some(None) => self.thread = get_cur_env_id(),
+                            None => {}
+                        }
+                        String::from("OK")
+                    },
+                    None => String::from("E01")
+                },
+                b'T' => match parse_thread(args) {
+                    Some(Some(id)) if env_stat(id).is_ok() => String::from("OK"),
+                    _ => String::from("E01")
+                },
+                b'g' => reply_or_error(self.read_regs()),
+                b'G' => reply_or_error(self.write_regs(args).map(|_| String::from("OK"))),
+                b'p' => reply_or_error(self.read_reg(args)),
+                b'P' => reply_or_error(self.write_reg(args).map(|_| String::from("OK"))),
+                b'm' => reply_or_error(self.read_mem(args)),
+                b'M' => reply_or_error(self.write_mem(args).map(|_| String::from("OK"))),
+                b'c' => {
+                    if let (Some(pc), Some(id)) = (parse_hex(args), get_cur_env_id()) {
+                        let _ = env_trapframe(id, |tf| tf.cp0_epc = pc);
+                    }
+                    return Resume::Continue;
+                },
+                b'D' => {
+                    self.send_packet("OK");
+                    self.attached = false;
+                    info!("gdb: detached");
+                    return Resume::Continue;
+                },
+                b'k' => {
+                    self.attached = false;
+                    return Resume::Kill;
+                },
+                _ => String::new()
+            };
+            self.send_packet(&reply);
+        }
+    }
+
+    /// answer a `q` packet
+    fn query(&self, args: &str) -> String {
+        if args.starts_with("Supported") {
+            format!("PacketSize={:x}", PACKET_MAX)
+        } else if args == "fThreadInfo" {
+            let ids: Vec<String> = env_list().iter().map(|id| format!("{:x}", id)).collect();
+            format!("m{}", ids.join(","))
+        } else if args == "sThreadInfo" {
+            String::from("l")
+        } else if args == "C" {
+            match get_cur_env_id() {
+                Some(id) => format!("QC{:x}", id),
+                None => String::new()
+            }
+        } else if args == "Attached" {
+            String::from("1")
+        } else if let Some(id) = args.strip_prefix("ThreadExtraInfo,") {
+            let stat = parse_thread(id).flatten().ok_or(Error::BadEnv).and_then(env_stat);
+            match stat {
+                Ok(stat) => {
+                    let n = stat.env_name.iter().position(|&c| c == 0).unwrap_or(stat.env_name.len());
+                    let name = core::str::from_utf8(&stat.env_name[..n]).unwrap_or("?");
+                    let status = match stat.env_status {
+                        1 => "runnable",
+                        2 => "blocked",
+                        _ => "free"
+                    };
+                    hex_encode(format!("{} {}", name, status).as_bytes())
+                },
+                Err(_) => String::from("E01")
+            }
+        } else {
+            String::new()
+        }
+    }
+
+    /// selected thread
+    fn thread(&self) -> Result<EnvID, Error> {
+        self.thread.ok_or(Error::BadEnv)
+    }
+
+    /// encode all registers of selected thread
+    fn read_regs(&self) -> Result<String, Error> {
+        let regs = env_trapframe(self.thread()?, |tf| tf_regs(tf))?;
+        let mut out = String::new();
+        for reg in regs {
+            out.push_str(&hex_encode(&(reg as u32).to_le_bytes()));
+        }
+        Ok(out)
+    }
+
+    /// write registers of selected thread from a `G` packet
+    fn write_regs(&self, args: &str) -> Result<(), Error> {
+        let bytes = hex_decode(args).ok_or(Error::Inval)?;
+        env_trapframe(self.thread()?, |tf| {
+            for (n, word) in bytes.chunks_exact(4).take(NREGS).enumerate() {
+                set_tf_reg(tf, n, u32::from_le_bytes(word.try_into().unwrap()) as usize);
+            }
+        })
+    }
+
+    /// encode one register of selected thread, fpu registers are unavailable
+    fn read_reg(&self, args: &str) -> Result<String, Error> {
+        let n = parse_hex(args).ok_or(Error::Inval)?;
+        if n >= NREGS {
+            return Ok(String::from("xxxxxxxx"));
+        }
+        let reg = env_trapframe(self.thread()?, |tf| tf_regs(tf)[n])?;
+        Ok(hex_encode(&(reg as u32).to_le_bytes()))
+    }
+
+    /// write one register of selected thread from a `P` packet
+    fn write_reg(&self, args: &str) -> Result<(), Error> {
+        let (n, value) = args.split_once('=').ok_or(Error::Inval)?;
+        let n = parse_hex(n).ok_or(Error::Inval)?;
+        let value = hex_decode(value).ok_or(Error::Inval)?;
+        let value: [u8; 4] = value.try_into().map_err(|_| Error::Inval)?;
+        if n >= NREGS {
+            return Ok(());
+        }
+        env_trapframe(self.thread()?, |tf| set_tf_reg(tf, n, u32::from_le_bytes(value) as usize))
+    }
+
+    /// read memory, user addresses through page table of selected thread
+    fn read_mem(&self, args: &str) -> Result<String, Error> {
+        let (addr, len) = parse_range(args).ok_or(Error::Inval)?;
+        let len = len.min(PACKET_MAX / 2);
+        if addr < ULIM {
+            return Ok(hex_encode(&env_read(self.thread()?, VirtAddr::new(addr), len)?));
+        }
+        check_kernel_range(addr, len)?;
+        let bytes: Vec<u8> = (addr..addr + len).map(|a| unsafe { read_volatile(a as *const u8) }).collect();
+        Ok(hex_encode(&bytes))
+    }
+
+    /// write memory from an `M` packet, user addresses through page table of selected thread
+    fn write_mem(&self, args: &str) -> Result<(), Error> {
+        let (range, data) = args.split_once(':').ok_or(Error::Inval)?;
+        let (addr, len) = parse_range(range).ok_or(Error::Inval)?;
+        let data = hex_decode(data).ok_or(Error::Inval)?;
+        if data.len() != len {
+            return Err(Error::Inval);
+        }
+        if addr < ULIM {
+            return env_write(self.thread()?, VirtAddr::new(addr), &data);
+        }
+        check_kernel_range(addr, len)?;
+        for (i, byte) in data.iter().enumerate() {
+            unsafe { write_volatile((addr + i) as *mut u8, *byte) };
+        }
+        Ok(())
+    }
+}
+
+/// registers of a trap frame in gdb order
+fn tf_regs(tf: &Trapframe) -> [usize; NREGS] {
+    let mut regs = [0; NREGS];
+    regs[..32].copy_from_slice(&tf.regs);
+    regs[32] = tf.cp0_status;
+    regs[33] = tf.lo;
+    regs[34] = tf.hi;
+    regs[35] = tf.cp0_badvaddr;
+    regs[36] = tf.cp0_cause;
+    regs[37] = tf.cp0_epc;
+    regs
+}
+
+/// set a register of a trap frame in gdb order, status, badvaddr and cause are read only
+fn set_tf_reg(tf: &mut Trapframe, n: usize, value: usize) {
+    match n {
+        1..=31 => tf.regs[n] = value,
+        33 => tf.lo = value,
+        34 => tf.hi = value,
+        37 => tf.cp0_epc = value,
+        _ => {}
+    }
+}
+
+/// only ram in kseg0 can be accessed without faulting, device registers in kseg1 are never touched
+fn check_kernel_range(addr: usize, len: usize) -> Result<(), Error> {
+    let (nframes, _) = frame_stats().ok_or(Error::Inval)?;
+    match addr.checked_add(len) {
+        Some(end) if addr >= KSEG0 && end <= KSEG0 + (nframes << PGSHIFT) => Ok(()),
+        _ => Err(Error::Inval)
+    }
+}
+
+/// reply with an error packet on failure
+fn reply_or_error(result: Result<String, Error>) -> String {
+    match result {
+        Ok(reply) => reply,
+        Err(err) => format!("E{:02x}", err as i32)
+    }
+}
+
+/// value of a hex digit
+fn hex_value(c: u8) -> Option<usize> {
+    (c as char).to_digit(16).map(|d| d as usize)
+}
+
+/// parse a hex number
+fn parse_hex(s: &str) -> Option<usize> {
+    usize::from_str_radix(s, 16).ok()
+}
+
+/// parse `addr,len`
+fn parse_range(s: &str) -> Option<(usize, usize)> {
+    let (addr, len) = s.split_once(',')?;
+    Some((parse_hex(addr)?, parse_hex(len)?))
+}
+
+/// parse a thread id, `0` and `-1` mean any thread
+fn parse_thread(s: &str) -> Option<Option<EnvID>> {
+    match s {
+        "0" | "-1" => Some(None),
+        _ => parse_hex(s).map(|id| Some(EnvID::new(id)))
+    }
+}
+
+/// encode bytes as hex
+fn hex_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for byte in bytes {
+        let _ = write!(out, "{:02x}", byte);
+    }
+    out
+}
+
+/// decode hex into bytes
+fn hex_decode(s: &str) -> Option<Vec<u8>> {
+    let s = s.as_bytes();
+    if s.len() % 2 != 0 {
+        return None;
+    }
+    s.chunks_exact(2).map(|pair| Some((hex_value(pair[0])? << 4 | hex_value(pair[1])?) as u8)).collect()
+}
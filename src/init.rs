@@ -1,26 +1,90 @@
+use core::ffi::CStr;
+
+use crate::clock::{self, DEFAULT_HZ};
+use crate::device::{i8259, rtc, serial};
 use crate::env;
 use crate::env::schedule;
+use crate::exception::irq;
+use crate::gdb;
 use crate::env::sem;
 use crate::env_create_pri;
 use crate::memory;
-use crate::println;
+use crate::info;
+use crate::log;
 use crate::memory::*;
 use crate::env::bare::*;
 
 pub struct Init;
 
+/// options passed on kernel command line
+pub struct BootArgs {
+    /// timer tick rate, set by `hz=`
+    pub hz: usize,
+    /// names of envs whose syscalls are printed, set by `strace=`
+    pub strace: &'static str,
+    /// most verbose level kept in the log buffer, set by `log=`
+    pub log: Option<&'static str>,
+    /// most verbose level printed on console, set by `loglevel=`
+    pub loglevel: Option<&'static str>,
+    /// per-module log levels like `env=trace,memory::shm=info`, set by `logmod=`
+    pub logmod: &'static str,
+    /// check frame ref counts after every env free, set by `memcheck=1`
+    pub memcheck: bool,
+}
+
+impl BootArgs {
+    /// parse `key=value` options from argv, argv[0] is the kernel name
+    pub fn parse(argc: u32, argv: *const *const u8) -> Self {
+        let mut args = Self { hz: DEFAULT_HZ, strace: "", log: None, loglevel: None, logmod: "", memcheck: false };
+        for i in 1..argc as usize {
+            let arg = unsafe { CStr::from_ptr(*argv.add(i) as *const _) };
+            let Ok(arg) = arg.to_str() else { continue };
+            for opt in arg.split_whitespace() {
+                if let Some(hz) = opt.strip_prefix("hz=") {
+                    args.hz = hz.parse().unwrap_or(DEFAULT_HZ);
+                } else if let Some(names) = opt.strip_prefix("strace=") {
+                    args.strace = names;
+                } else if let Some(level) = opt.strip_prefix("log=") {
+                    args.log = Some(level);
+                } else if let Some(level) = opt.strip_prefix("loglevel=") {
+                    args.loglevel = Some(level);
+                } else if let Some(filters) = opt.strip_prefix("logmod=") {
+                    args.logmod = filters;
+                } else if let Some(on) = opt.strip_prefix("memcheck=") {
+                    args.memcheck = on == "1";
+                }
+            }
+        }
+        args
+    }
+}
+
 impl Init {
-    pub fn init(&mut self, ram_low_size: usize) {
-        println!("mos init");
+    pub fn init(&mut self, ram_low_size: usize, args: BootArgs) {
+        log::log_init(args.log, args.loglevel, args.logmod);
+        i8259::init();
+        clock::clock_init(args.hz);
+        irq::register_irq(irq::TIMER_IRQ, clock::clock_intr).unwrap();
+        serial::serial_init();
+        gdb::gdb_init();
+        info!("mos init");
+        info!("clock: {} hz", clock::clock_hz());
+        rtc::rtc_init();
         heap::init_heap();
         memory::init_memory(ram_low_size);
+        #[cfg(test)]
+        crate::test_main();
 
         env::env_init();
+        env::strace::strace_init(args.strace);
         shm::init();
         sem::init();
         
         env_create_pri!(USER_ICODE, 1);
-        env_create_pri!(FS_SERV, 1);
+        env::oom::oom_protect(env_create_pri!(FS_SERV, 1));
+        env::memcheck::memcheck_init(args.memcheck);
+        let check = env::memcheck::memcheck();
+        info!("memcheck: {} frames used, {} problems", check.mc_used, check.problems());
         
         schedule::schedule(0);
     }
@@ -28,6 +92,6 @@ impl Init {
 
 /// rust entry
 #[no_mangle]
-pub extern "C" fn rust_main(_argc: u32, _argv: *const *const u8, _penv: *const *const u8, ram_low_size: usize) {
-    Init.init(ram_low_size);
+pub extern "C" fn rust_main(argc: u32, argv: *const *const u8, _penv: *const *const u8, ram_low_size: usize) {
+    Init.init(ram_low_size, BootArgs::parse(argc, argv));
 }
\ No newline at end of file
@@ -0,0 +1,172 @@
+use core::fmt::{self, Write};
+
+use crate::{clock::uptime_ticks, print::Stdout, sync::cell::UPSafeCell, util::ring_buffer::RingBuffer};
+
+/// bytes of log text kept in memory, the oldest text is dropped when full
+pub const LOG_BUF_LEN: usize = 16384;
+
+/// global kernel logger
+pub static LOG: UPSafeCell<Logger> = UPSafeCell::new(Logger::new());
+
+/// log levels, a lower level is more severe
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[repr(usize)]
+pub enum Level {
+    Error = 1,
+    Warn = 2,
+    Info = 3,
+    Debug = 4,
+    Trace = 5
+}
+
+impl Level {
+    /// parse a level name or number
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "error" | "1" => Some(Level::Error),
+            "warn" | "2" => Some(Level::Warn),
+            "info" | "3" => Some(Level::Info),
+            "debug" | "4" => Some(Level::Debug),
+            "trace" | "5" => Some(Level::Trace),
+            _ => None
+        }
+    }
+
+    /// tag written before a buffered message
+    fn tag(self) -> &'static str {
+        match self {
+            Level::Error => "E",
+            Level::Warn => "W",
+            Level::Info => "I",
+            Level::Debug => "D",
+            Level::Trace => "T"
+        }
+    }
+}
+
+/// kernel log buffer and filters
+pub struct Logger {
+    buf: RingBuffer<u8, LOG_BUF_LEN>,
+    /// messages up to this level are kept in the buffer
+    level: Level,
+    /// kept messages up to this level are also printed on console
+    console: Level,
+    /// `module=level` overrides of level separated by commas
+    filters: &'static str
+}
+
+impl Write for Logger {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            self.buf.push_overwrite(byte);
+        }
+        Ok(())
+    }
+}
+
+impl Logger {
+    /// create an empty logger keeping debug messages and printing info messages
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            buf: RingBuffer::new(0),
+            level: Level::Debug,
+            console: Level::Info,
+            filters: ""
+        }
+    }
+
+    /// most verbose level kept for a module, the longest matching filter wins
+    fn max_level(&self, module: &str) -> Level {
+        let mut best = (0, self.level);
+        for filter in self.filters.split(',') {
+            let (prefix, level) = match filter.split_once('=') {
+                Some((prefix, level)) => (prefix, Level::parse(level)),
+                None => continue
+            };
+            let matched = module == prefix || module.strip_prefix(prefix).is_some_and(|rest| rest.starts_with("::"));
+            if let (true, Some(level)) = (matched, level) {
+                if prefix.len() >= best.0 {
+                    best = (prefix.len(), level);
+                }
+            }
+        }
+        best.1
+    }
+
+    /// copy the newest retained text into dst, return number of bytes copied
+    pub fn read(&self, dst: &mut [u8]) -> usize {
+        let n = dst.len().min(self.buf.len());
+        let skip = self.buf.len() - n;
+        for (i, byte) in dst[..n].iter_mut().enumerate() {
+            *byte = self.buf.get(skip + i).unwrap();
+        }
+        n
+    }
+}
+
+/// set levels and module filters from boot options, None keeps the default
+pub fn log_init(level: Option<&str>, console: Option<&str>, filters: &'static str) {
+    let mut log = LOG.borrow_mut();
+    if let Some(level) = level.and_then(Level::parse) {
+        log.level = level;
+    }
+    if let Some(console) = console.and_then(Level::parse) {
+        log.console = console;
+    }
+    log.filters = filters;
+}
+
+/// log implementation, keep a message as `[tick] L module: text` and print it if severe enough
+pub fn _log(level: Level, module: &str, args: fmt::Arguments) {
+    let module = module.split_once("::").map_or("", |(_, rest)| rest);
+    let mut log = LOG.borrow_mut();
+    if level > log.max_level(module) {
+        return;
+    }
+    let _ = write!(log, "[{:>8}] {} {}: ", uptime_ticks(), level.tag(), module);
+    let _ = log.write_fmt(args);
+    let _ = log.write_str("\n");
+    if level <= log.console {
+        let _ = Stdout.write_fmt(args);
+        let _ = Stdout.write_str("\n");
+    }
+}
+
+/// log a message at given level
+#[macro_export]
+macro_rules! log {
+    ($level: expr, $fmt: literal $(, $($arg: tt)+)?) => {
+        $crate::log::_log($level, module_path!(), format_args!($fmt $(, $($arg)+)?))
+    };
+}
+
+/// log an error
+#[macro_export]
+macro_rules! error {
+    ($($arg: tt)+) => { $crate::log!($crate::log::Level::Error, $($arg)+) };
+}
+
+/// log a warning
+#[macro_export]
+macro_rules! warn {
+    ($($arg: tt)+) => { $crate::log!($crate::log::Level::Warn, $($arg)+) };
+}
+
+/// log an informational message
+#[macro_export]
+macro_rules! info {
+    ($($arg: tt)+) => { $crate::log!($crate::log::Level::Info, $($arg)+) };
+}
+
+/// log a debug message
+#[macro_export]
+macro_rules! debug {
+    ($($arg: tt)+) => { $crate::log!($crate::log::Level::Debug, $($arg)+) };
+}
+
+/// log a trace message
+#[macro_export]
+macro_rules! trace {
+    ($($arg: tt)+) => { $crate::log!($crate::log::Level::Trace, $($arg)+) };
+}
@@ -7,6 +7,9 @@
 #![allow(dead_code)]
 #![no_std]
 #![no_main]
+#![cfg_attr(test, feature(custom_test_frameworks))]
+#![cfg_attr(test, test_runner(crate::test::test_runner))]
+#![cfg_attr(test, reexport_test_harness_main = "test_main")]
 
 use core::arch::global_asm;
 
@@ -14,7 +17,10 @@ extern crate alloc;
 
 /// kernel print
 pub mod print;
+/// kernel log
+pub mod log;
 /// kernel panic
+#[cfg(not(test))]
 pub mod panic;
 /// kernel init
 pub mod init;
@@ -32,9 +38,19 @@ pub mod util;
 pub mod env;
 /// for sync
 pub mod sync;
+/// timer and uptime
+pub mod clock;
+/// gdb remote stub
+pub mod gdb;
+/// kernel monitor
+pub mod monitor;
+/// kernel test harness
+#[cfg(test)]
+pub mod test;
 
 global_asm!(include_str!("init/start.gen.S"));
 global_asm!(include_str!("memory/tlb_asm.gen.S"));
 global_asm!(include_str!("env/env_asm.gen.S"));
 global_asm!(include_str!("exception/genex.gen.S"));
-global_asm!(include_str!("exception/entry.gen.S"));
\ No newline at end of file
+global_asm!(include_str!("exception/entry.gen.S"));
+global_asm!(include_str!("clock/clock_asm.gen.S"));
\ No newline at end of file
@@ -1,6 +1,6 @@
 use core::{mem::size_of, ptr::write_bytes};
 
-use alloc::vec::Vec;
+use alloc::{vec, vec::Vec};
 
 use crate::{err::Error, sync::cell::UPSafeCell, util::queue::IndexLink};
 
@@ -18,6 +18,9 @@ pub fn frame_dealloc(ppn: PhysPageNum) { FRAME_ALLOCATOR.borrow_mut().dealloc(pp
 /// increase ref count for frame
 #[inline]
 pub fn frame_incref(ppn: PhysPageNum) { FRAME_ALLOCATOR.borrow_mut().frames[ppn.as_usize()].pf_ref += 1; }
+/// get ref count of frame
+#[inline]
+pub fn frame_ref(ppn: PhysPageNum) -> usize { FRAME_ALLOCATOR.borrow_mut().frames[ppn.as_usize()].pf_ref as usize }
 /// init frame allocator
 #[inline]
 pub fn init_frame_allocator(freemem: VirtAddr, nframes: usize) { FRAME_ALLOCATOR.borrow_mut().init(freemem, nframes); }
@@ -33,6 +36,11 @@ pub fn frame_base_size() -> usize { FRAME_ALLOCATOR.borrow_mut().base_size }
 /// number of free frames
 #[inline]
 pub fn num_free_frames() -> usize { FRAME_ALLOCATOR.borrow_mut().frames_free_list.len() }
+/// number of frames and free frames, None if the allocator is in use
+#[inline]
+pub fn frame_stats() -> Option<(usize, usize)> { FRAME_ALLOCATOR.try_borrow_mut().map(|fa| (fa.nframes, fa.frames_free_list.len())) }
+/// ref counts and free list membership of all frames, for the memory checker
+pub fn frame_snapshot() -> FrameSnapshot { FRAME_ALLOCATOR.borrow_mut().snapshot() }
 /// clear a frame by setting 0
 #[inline]
 fn frame_clear(ppn: PhysPageNum) {
@@ -45,10 +53,20 @@ fn frame_clear(ppn: PhysPageNum) {
 pub struct PhysFrame {
     pf_ref: u16
 }
+/// state of all frames at one point
+pub struct FrameSnapshot {
+    /// ref count of each frame
+    pub refs: Vec<u16>,
+    /// whether each frame is on the free list
+    pub free: Vec<bool>,
+    /// frames below this are used by the kernel image and hold one ref each
+    pub reserved: usize
+}
 /// frame allocator
 pub struct FrameAllocator {
     frames: Vec<PhysFrame>,
     nframes: usize,
+    nreserved: usize,
     frames_free_list: IndexLink,
     base_addr: VirtAddr,
     base_size: usize
@@ -61,6 +79,7 @@ impl FrameAllocator {
         Self {
             frames: Vec::new(),
             nframes: 0,
+            nreserved: 0,
             frames_free_list: IndexLink::new(),
             base_addr: VirtAddr::new(0),
             base_size: 0
@@ -76,6 +95,7 @@ impl FrameAllocator {
         self.nframes = nframes;
 
         let used_ppn = PhysPageNum::from(PhysAddr::from_kva(freemem));
+        self.nreserved = used_ppn.as_usize();
         for ppn in PhysPageNum::new(0)..used_ppn {
             self.frames[ppn.as_usize()].pf_ref = 1;
         }
@@ -125,6 +145,18 @@ impl FrameAllocator {
     fn pop_first(&mut self) {
         self.frames_free_list.remove(self.frames_free_list.first().unwrap());
     }
+    /// take a snapshot of ref counts and the free list
+    pub fn snapshot(&self) -> FrameSnapshot {
+        let mut free = vec![false; self.nframes];
+        for ind in self.frames_free_list.iter() {
+            free[ind] = true;
+        }
+        FrameSnapshot {
+            refs: self.frames.iter().map(|frame| frame.pf_ref).collect(),
+            free,
+            reserved: self.nreserved
+        }
+    }
     /// frames base physical address
     #[inline]
     pub fn base_phy_addr(&mut self) -> PhysAddr {
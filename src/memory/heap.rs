@@ -163,6 +163,12 @@ pub fn init_heap() {
     HEAP_ALLOCATOR.init(start, KERNEL_HEAP_SIZE);
 }
 
+/// requested, allocated and total bytes of kernel heap, None if the heap is in use
+pub fn heap_stats() -> Option<(usize, usize, usize)> {
+    let heap = HEAP_ALLOCATOR.0.try_borrow_mut()?;
+    Some((heap.stats_alloc_user(), heap.stats_alloc_actual(), heap.stats_total_bytes()))
+}
+
 impl<const ORDER: usize> HeapAllocator<ORDER> {
     /// create an empty heap allocator
     pub const fn empty() -> Self {
@@ -191,4 +197,71 @@ unsafe impl<const ORDER: usize> GlobalAlloc for HeapAllocator<ORDER> {
 #[alloc_error_handler]
 pub fn handle_heap_alloc_error(layout: core::alloc::Layout) -> ! {
     panic!("Heap allocation error, layout={:?}", layout);
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use core::{alloc::Layout, mem::size_of_val};
+
+    use super::Heap;
+
+    /// space of a test heap, aligned to be a single buddy block
+    #[repr(align(4096))]
+    struct Space([u8; 4096]);
+
+    fn heap(space: &mut Space) -> Heap<32> {
+        let mut heap = Heap::<32>::new();
+        heap.init(space.0.as_mut_ptr() as usize, size_of_val(space));
+        heap
+    }
+
+    #[test_case]
+    fn alloc_dealloc() {
+        let mut space = Space([0; 4096]);
+        let mut heap = heap(&mut space);
+        assert_eq!(heap.stats_total_bytes(), 4096);
+        let layout = Layout::from_size_align(100, 8).unwrap();
+        let a = heap.alloc(layout).unwrap();
+        let b = heap.alloc(layout).unwrap();
+        assert!(a != b);
+        assert_eq!(heap.stats_alloc_user(), 200);
+        assert_eq!(heap.stats_alloc_actual(), 256);
+        heap.dealloc(a, layout);
+        heap.dealloc(b, layout);
+        assert_eq!(heap.stats_alloc_user(), 0);
+        assert_eq!(heap.stats_alloc_actual(), 0);
+    }
+
+    #[test_case]
+    fn buddies_merge() {
+        let mut space = Space([0; 4096]);
+        let start = space.0.as_ptr() as usize;
+        let mut heap = heap(&mut space);
+        let half = Layout::from_size_align(2048, 8).unwrap();
+        let a = heap.alloc(half).unwrap();
+        let b = heap.alloc(half).unwrap();
+        assert!(heap.alloc(half).is_err());
+        heap.dealloc(a, half);
+        heap.dealloc(b, half);
+        let whole = heap.alloc(Layout::from_size_align(4096, 8).unwrap()).unwrap();
+        assert_eq!(whole.as_ptr() as usize, start);
+    }
+
+    #[test_case]
+    fn alignment() {
+        let mut space = Space([0; 4096]);
+        let mut heap = heap(&mut space);
+        let small = heap.alloc(Layout::from_size_align(8, 8).unwrap()).unwrap();
+        let aligned = heap.alloc(Layout::from_size_align(8, 256).unwrap()).unwrap();
+        assert!(small != aligned);
+        assert_eq!(aligned.as_ptr() as usize % 256, 0);
+    }
+
+    #[test_case]
+    fn too_large() {
+        let mut space = Space([0; 4096]);
+        let mut heap = heap(&mut space);
+        assert!(heap.alloc(Layout::from_size_align(8192, 8).unwrap()).is_err());
+        assert_eq!(heap.stats_alloc_actual(), 0);
+    }
+}
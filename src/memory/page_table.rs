@@ -1,10 +1,16 @@
-use core::ptr::addr_of_mut;
+use core::{cmp::min, ptr::{addr_of_mut, copy}};
 
-use crate::{env::ASID, err::Error};
+use alloc::{collections::BTreeMap, vec::Vec};
+
+use crate::{env::ASID, err::Error, sync::cell::UPSafeCell};
 
 use super::{frame::*, mmu::*, tlb::tlb_invalidate};
 pub const PAGE_TABLE_ENTRIES: usize = PAGE_SIZE / 4;
 
+/// number of pages mapped below UTOP in each page dir, keyed by its address.
+/// kept by insert and remove so it is cheap to read on every switch and refill
+static RESIDENT: UPSafeCell<BTreeMap<usize, usize>> = UPSafeCell::new(BTreeMap::new());
+
 /// Page table entry, wrapped type.
 /// In original mos, there are pte and pde.
 /// For better abstraction on paging, pte refers to both pte and pde in mos-rust.
@@ -118,6 +124,9 @@ impl PageTable {
                 frame_decref(ppn);
                 *pte = Pte::new(0);
                 tlb_invalidate(asid, va);
+                if va < UTOP {
+                    self.uncount_resident();
+                }
             },
             Err(_) => return,
         }
@@ -140,9 +149,49 @@ impl PageTable {
         let pte = self.walk_or_create(va, 1)?;
         frame_incref(ppn);
         *pte = Pte::new_from_ppn(ppn, perm | PTE_C_CACHEABLE | PTE_V);
+        if va < UTOP {
+            *RESIDENT.borrow_mut().entry(self.key()).or_insert(0) += 1;
+        }
         Ok(())
     }
 
+    /// number of pages mapped below UTOP, same as `count_mapped(UTOP)` without walking the tables
+    #[inline]
+    pub fn resident(&self) -> usize {
+        RESIDENT.borrow_mut().get(&self.key()).copied().unwrap_or(0)
+    }
+
+    /// key of this page dir in RESIDENT
+    #[inline]
+    fn key(&self) -> usize {
+        self as *const Self as usize
+    }
+
+    /// take a removed page off the resident count, the entry goes away with the last page
+    fn uncount_resident(&self) {
+        let mut resident = RESIDENT.borrow_mut();
+        let key = self.key();
+        if let Some(count) = resident.get_mut(&key) {
+            *count -= 1;
+            if *count == 0 {
+                resident.remove(&key);
+            }
+        }
+    }
+
+    /// give va a frame of its own before the kernel writes to it.
+    /// a frame shared with other mappings is copied, library pages stay shared.
+    pub fn make_private(&mut self, asid: ASID, va: VirtAddr) -> Result<(), Error> {
+        let (ppn, pte) = self.lookup(va)?;
+        let perm = pte.perm();
+        if frame_ref(ppn) <= 1 || perm & PTE_LIBRARY != 0 {
+            return Ok(());
+        }
+        let new = frame_alloc()?;
+        unsafe { copy(ppn.into_kva().as_ptr::<u8>(), new.into_kva().as_mut_ptr::<u8>(), PAGE_SIZE); }
+        self.insert(asid, new, va.page_align_down(), perm)
+    }
+
     /// translate virtual address to physical address
     #[inline]
     pub fn translate(&self, va: VirtAddr) -> Option<PhysAddr> {
@@ -161,6 +210,42 @@ impl PageTable {
         }
     }
 
+    /// page dir entry and page table entry of va, no pte if its page table is not present
+    pub fn walk(&self, va: VirtAddr) -> (Pte, Option<Pte>) {
+        let pde = self.entries[va.pdx()];
+        if !pde.valid() {
+            return (pde, None);
+        }
+        let pt = unsafe { &*pde.ppn().into_kva().as_ptr::<PageTable>() };
+        (pde, Some(pt.entries[va.ptx()]))
+    }
+
+    /// copy bytes to address space of this page table through kernel addresses
+    pub fn copy_out(&self, va: VirtAddr, src: &[u8]) -> Result<(), Error> {
+        let mut done = 0;
+        while done < src.len() {
+            let cur = va + done;
+            let pa = self.translate(cur).ok_or(Error::NotMapped)?;
+            let n = min(src.len() - done, PAGE_SIZE - cur.page_offset());
+            unsafe { copy(src[done..].as_ptr(), pa.into_kva().as_mut_ptr::<u8>(), n); }
+            done += n;
+        }
+        Ok(())
+    }
+
+    /// copy bytes from address space of this page table through kernel addresses
+    pub fn copy_in(&self, va: VirtAddr, dst: &mut [u8]) -> Result<(), Error> {
+        let mut done = 0;
+        while done < dst.len() {
+            let cur = va + done;
+            let pa = self.translate(cur).ok_or(Error::NotMapped)?;
+            let n = min(dst.len() - done, PAGE_SIZE - cur.page_offset());
+            unsafe { copy(pa.into_kva().as_ptr::<u8>(), dst[done..].as_mut_ptr(), n); }
+            done += n;
+        }
+        Ok(())
+    }
+
     /// map segment in page table
     #[inline]
     pub fn map_segment(&mut self, asid: ASID, pa: PhysAddr, va: VirtAddr, size: usize, perm: usize) {
@@ -185,49 +270,164 @@ impl PageTable {
         self.entries[ptx]
     }
 
-    /// alloc frames passively
-    fn passive_alloc(&mut self, va: VirtAddr, asid: ASID) {
-        if va < UTEMP {
-            panic!("address too low");
-        }
-    
-        if va >= USTACKTOP && va < USTACKTOP + PAGE_SIZE {
-            panic!("invalid memory");
-        }
-    
-        if va >= UENVS && va < UPAGES {
-            panic!("envs zone");
+    /// count mapped pages below limit
+    pub fn count_mapped(&self, limit: VirtAddr) -> usize {
+        let mut count = 0;
+        for pdeno in 0..limit.pdx() {
+            let pde = self.entries[pdeno];
+            if !pde.valid() {
+                continue;
+            }
+            let pt = unsafe { &*pde.ppn().into_kva().as_ptr::<PageTable>() };
+            count += pt.entries.iter().filter(|pte| pte.valid()).count();
         }
-    
-        if va >= UPAGES && va  < UVPT {
-            panic!("pages zone");
+        count
+    }
+
+    /// valid mappings below limit with their page addresses, in address order
+    pub fn mapped(&self, limit: VirtAddr) -> Vec<(VirtAddr, Pte)> {
+        let mut found = Vec::new();
+        for pdeno in 0..limit.pdx() {
+            let pde = self.entries[pdeno];
+            if !pde.valid() {
+                continue;
+            }
+            let pt = unsafe { &*pde.ppn().into_kva().as_ptr::<PageTable>() };
+            for (pteno, pte) in pt.entries.iter().enumerate() {
+                if pte.valid() {
+                    found.push((VirtAddr::new((pdeno << PDSHIFT) | (pteno << PGSHIFT)), *pte));
+                }
+            }
         }
-    
+        found
+    }
+
+    /// alloc frames passively, fails with `Error::Inval` for addresses no page may be allocated at
+    fn passive_alloc(&mut self, va: VirtAddr, asid: ASID) -> Result<(), Error> {
         if va.as_usize() >= ULIM {
             panic!("kernel address");
         }
-        let ppn = frame_alloc().unwrap();
+        if va < UTEMP
+            || (va >= USTACKTOP && va < USTACKTOP + PAGE_SIZE)
+            || (va >= UENVS && va < UVPT) {
+            return Err(Error::Inval);
+        }
+        let ppn = frame_alloc()?;
         frame_incref(ppn);
-        self.insert(asid, ppn, va.page_align_down(), 
+        let result = self.insert(asid, ppn, va.page_align_down(), 
             if va >= UVPT && va.as_usize() < ULIM {
                 0
             } else {
                 PTE_D
-            }).unwrap();
+            });
+        frame_decref(ppn);
+        result
     }
 
-    /// do tlb refill according to page table
+    /// do tlb refill according to page table, return if a page is allocated passively.
+    /// fails with `Error::NoMem` when the page cannot be allocated, `Error::Inval` for a bad address.
     #[inline]
-    pub fn do_tlb_refill(&mut self, entries: &mut [usize; 2], va: VirtAddr, asid: ASID) {
+    pub fn do_tlb_refill(&mut self, entries: &mut [usize; 2], va: VirtAddr, asid: ASID) -> Result<bool, Error> {
         tlb_invalidate(asid, va);
 
+        let mut allocated = false;
         let pte = loop {
             if let Ok((_, pte)) = self.lookup(va) {
                 break pte;
             }
-            self.passive_alloc(va, asid);
+            self.passive_alloc(va, asid)?;
+            allocated = true;
         };
 
         pte.fill_tlb_entry(entries);
+        Ok(allocated)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{memory::{frame::{frame_alloc, frame_ref, num_free_frames}, mmu::{PAGE_SIZE, PGSHIFT, PTE_D, PTE_V, UTEMP, UTOP}}, test::TestPgdir};
+
+    #[test_case]
+    fn insert_lookup_remove() {
+        let free = num_free_frames();
+        {
+            let mut pgdir = TestPgdir::new();
+            let asid = pgdir.asid;
+            let ppn = frame_alloc().unwrap();
+            let va = UTEMP + 3 * PAGE_SIZE;
+            pgdir.pgdir().insert(asid, ppn, va, PTE_D).unwrap();
+            assert_eq!(frame_ref(ppn), 1);
+            let (found, pte) = pgdir.pgdir().lookup(va).unwrap();
+            assert!(found == ppn);
+            assert_eq!(pte.perm() & (PTE_D | PTE_V), PTE_D | PTE_V);
+            let pa = pgdir.pgdir().translate(va + 0x123).unwrap();
+            assert_eq!(pa.as_usize(), (ppn.as_usize() << PGSHIFT) + 0x123);
+            pgdir.pgdir().remove(asid, va);
+            assert!(pgdir.pgdir().lookup(va).is_err());
+            assert!(pgdir.pgdir().translate(va).is_none());
+        }
+        assert_eq!(num_free_frames(), free);
+    }
+
+    #[test_case]
+    fn insert_replaces() {
+        let mut pgdir = TestPgdir::new();
+        let asid = pgdir.asid;
+        let (a, b) = (frame_alloc().unwrap(), frame_alloc().unwrap());
+        pgdir.pgdir().insert(asid, a, UTEMP, PTE_D).unwrap();
+        pgdir.pgdir().insert(asid, b, UTEMP, PTE_D).unwrap();
+        assert_eq!(frame_ref(a), 0);
+        assert_eq!(frame_ref(b), 1);
+        assert!(pgdir.pgdir().lookup_ppn(UTEMP).unwrap() == b);
+        pgdir.pgdir().insert(asid, b, UTEMP, 0).unwrap();
+        assert_eq!(frame_ref(b), 1);
+        assert_eq!(pgdir.pgdir().lookup(UTEMP).unwrap().1.perm() & PTE_D, 0);
+    }
+
+    #[test_case]
+    fn shared_frame_refs() {
+        let mut pgdir = TestPgdir::new();
+        let asid = pgdir.asid;
+        let ppn = frame_alloc().unwrap();
+        pgdir.pgdir().insert(asid, ppn, UTEMP, 0).unwrap();
+        pgdir.pgdir().insert(asid, ppn, UTEMP + 5 * PAGE_SIZE, 0).unwrap();
+        assert_eq!(frame_ref(ppn), 2);
+        assert_eq!(pgdir.pgdir().count_mapped(UTOP), 2);
+        assert_eq!(pgdir.pgdir().resident(), 2);
+        let mapped = pgdir.pgdir().mapped(UTOP);
+        assert!(mapped[0].0 == UTEMP && mapped[1].0 == UTEMP + 5 * PAGE_SIZE);
+        pgdir.pgdir().remove(asid, UTEMP);
+        assert_eq!(frame_ref(ppn), 1);
+        assert_eq!(pgdir.pgdir().resident(), 1);
     }
-}
\ No newline at end of file
+
+    #[test_case]
+    fn copy_across_pages() {
+        let mut pgdir = TestPgdir::new();
+        let asid = pgdir.asid;
+        for i in 0..2 {
+            let ppn = frame_alloc().unwrap();
+            pgdir.pgdir().insert(asid, ppn, UTEMP + i * PAGE_SIZE, PTE_D).unwrap();
+        }
+        let va = UTEMP + (PAGE_SIZE - 2);
+        pgdir.pgdir().copy_out(va, b"abcd").unwrap();
+        let mut buf = [0; 4];
+        pgdir.pgdir().copy_in(va, &mut buf).unwrap();
+        assert_eq!(&buf, b"abcd");
+        assert!(pgdir.pgdir().copy_in(UTEMP + (2 * PAGE_SIZE - 2), &mut buf).is_err());
+    }
+
+    #[test_case]
+    fn walk_entries() {
+        let mut pgdir = TestPgdir::new();
+        let asid = pgdir.asid;
+        let (pde, pte) = pgdir.pgdir().walk(UTEMP);
+        assert!(!pde.valid() && pte.is_none());
+        let ppn = frame_alloc().unwrap();
+        pgdir.pgdir().insert(asid, ppn, UTEMP, 0).unwrap();
+        let (pde, pte) = pgdir.pgdir().walk(UTEMP);
+        assert!(pde.valid());
+        assert!(pte.is_some_and(|pte| pte.valid() && pte.ppn() == ppn));
+    }
+}
@@ -1,4 +1,6 @@
-use crate::{env::ASID, err::Error, println, sync::cell::UPSafeCell};
+use alloc::vec::Vec;
+
+use crate::{debug, env::ASID, err::Error, sync::cell::UPSafeCell};
 use super::{frame::{frame_alloc, frame_decref, frame_incref, num_free_frames}, mmu::{PhysPageNum, VirtAddr, PAGE_SIZE}, page_table::PageTable};
 
 pub const SHMALL: usize = 4096;
@@ -213,7 +215,7 @@ impl ShmManager {
     /// dealloc frames for shared memory
     #[inline]
     pub fn dealloc(&mut self, id: usize) {
-        println!("free shm[{}]", id);
+        debug!("free shm[{}]", id);
         let mut blk = self.shms[id].head;
         for _ in 0..self.shms[id].nblocks {
             frame_decref(self.blocks[blk].ppn);
@@ -222,6 +224,19 @@ impl ShmManager {
         self.shms[id].nblocks = 0;
     }
 
+    /// frames held by live shared memories
+    pub fn frames(&self) -> Vec<PhysPageNum> {
+        let mut frames = Vec::new();
+        for shm in self.shms.iter().filter(|shm| shm.nblocks > 0) {
+            let mut blk = shm.head;
+            for _ in 0..shm.nblocks {
+                frames.push(self.blocks[blk].ppn);
+                blk = self.blocks[blk].next.unwrap_or_default();
+            }
+        }
+        frames
+    }
+
     /// map shared memory at given address in user space
     #[inline]
     pub fn map(&mut self, id: usize, va: VirtAddr, asid: ASID, pgdir: &mut PageTable, perm: usize) -> Result<(), Error> {
@@ -234,4 +249,69 @@ impl ShmManager {
         }
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+
+    use crate::{err::Error, memory::{frame::{frame_ref, num_free_frames}, mmu::{PAGE_SIZE, PTE_D, UTEMP}}, test::TestPgdir};
+
+    use super::{ShmManager, SHMALL};
+
+    fn manager() -> Box<ShmManager> {
+        let mut shm = Box::new(ShmManager::new());
+        shm.init();
+        shm
+    }
+
+    #[test_case]
+    fn alloc_dealloc() {
+        let mut shm = manager();
+        let free = num_free_frames();
+        let id = shm.alloc(3).unwrap();
+        let frames = shm.frames();
+        assert_eq!(frames.len(), 3);
+        assert_eq!(num_free_frames(), free - 3);
+        assert!(frames.iter().all(|&ppn| frame_ref(ppn) == 1));
+        shm.dealloc(id);
+        assert!(shm.frames().is_empty());
+        assert_eq!(num_free_frames(), free);
+    }
+
+    #[test_case]
+    fn map_shares_frames() {
+        let mut shm = manager();
+        let mut pgdir = TestPgdir::new();
+        let asid = pgdir.asid;
+        let id = shm.alloc(2).unwrap();
+        shm.map(id, UTEMP, asid, pgdir.pgdir(), PTE_D).unwrap();
+        let frames = shm.frames();
+        for (i, &ppn) in frames.iter().enumerate() {
+            assert!(pgdir.pgdir().lookup_ppn(UTEMP + i * PAGE_SIZE).unwrap() == ppn);
+            assert_eq!(frame_ref(ppn), 2);
+        }
+        shm.dealloc(id);
+        assert!(frames.iter().all(|&ppn| frame_ref(ppn) == 1));
+    }
+
+    #[test_case]
+    fn removed_after_last_detach() {
+        let mut shm = manager();
+        let id = shm.alloc(1).unwrap();
+        shm.attach(id);
+        shm.attach(id);
+        shm.rmid(id);
+        shm.deattach(id);
+        assert_eq!(shm.shms[id].nblocks, 1);
+        shm.deattach(id);
+        assert_eq!(shm.shms[id].nblocks, 0);
+        assert!(shm.frames().is_empty());
+    }
+
+    #[test_case]
+    fn alloc_too_large() {
+        let mut shm = manager();
+        assert!(matches!(shm.alloc(SHMALL + 1), Err(Error::NoSpc)));
+    }
+}
@@ -1,10 +1,98 @@
 
-use crate::{env::{cur_pgdir, ASID}, exception::traps::Trapframe, util::bitops::genmask};
+use crate::{env::{coredump::env_crash, cur_check_frames, cur_pgdir, cur_rusage, env_enter_kernel, env_leave_kernel, oom::oom_kill, ASID}, err::Error, exception::traps::Trapframe, gdb::SIGSEGV, util::bitops::genmask};
 
-use super::mmu::{VirtAddr, NASID, PGSHIFT};
+use core::fmt::{self, Display};
+
+use alloc::vec::Vec;
+
+use super::mmu::{VirtAddr, NASID, PGSHIFT, PTE_D, PTE_G, PTE_HARDFLAG_SHIFT, PTE_V};
 
 extern "C" {
     fn tlb_out(entry: usize);
+    fn tlb_flush_all();
+    fn tlb_read_entry(index: usize, entry: &mut TlbEntry);
+    fn tlb_size() -> usize;
+}
+
+/// a tlb entry as read by tlbr, read by sys_tlb_read
+#[derive(Clone, Copy, Default)]
+#[repr(C)]
+pub struct TlbEntry {
+    pub te_hi: u32,
+    pub te_lo: [u32; 2],
+    pub te_pagemask: u32
+}
+
+impl TlbEntry {
+    /// virtual address of the even page
+    #[inline]
+    pub fn va(&self) -> VirtAddr {
+        VirtAddr::new(self.te_hi as usize & !genmask(PGSHIFT, 0))
+    }
+    /// virtual page number of the even and odd page pair
+    #[inline]
+    pub const fn vpn2(&self) -> usize {
+        self.te_hi as usize >> (PGSHIFT + 1)
+    }
+    /// asid of the entry
+    #[inline]
+    pub const fn asid(&self) -> usize {
+        self.te_hi as usize & (NASID - 1)
+    }
+    /// page frame number of the even (0) or odd (1) page
+    #[inline]
+    pub const fn pfn(&self, odd: usize) -> usize {
+        (self.te_lo[odd] >> PTE_HARDFLAG_SHIFT) as usize & 0xfffff
+    }
+    /// flags of the even (0) or odd (1) page, in pte layout
+    #[inline]
+    pub const fn flags(&self, odd: usize) -> usize {
+        (self.te_lo[odd] as usize & 0x3f) << PTE_HARDFLAG_SHIFT
+    }
+    /// entry matches all asids
+    #[inline]
+    pub const fn global(&self) -> bool {
+        self.flags(0) & self.flags(1) & PTE_G != 0
+    }
+    /// any page of the entry is valid
+    #[inline]
+    pub const fn valid(&self) -> bool {
+        (self.flags(0) | self.flags(1)) & PTE_V != 0
+    }
+}
+
+impl Display for TlbEntry {
+    /// format as `va asid mask` followed by `pfn flags` of both pages
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "va {:08x} asid {:02x} mask {:x}", self.va().as_usize(), self.asid(), self.te_pagemask)?;
+        for odd in 0..2 {
+            let flags = self.flags(odd);
+            write!(f, " | pfn {:05x} c{}{}{}{}", self.pfn(odd), flags >> (PTE_HARDFLAG_SHIFT + 3),
+                if flags & PTE_V != 0 { " V" } else { " -" },
+                if flags & PTE_D != 0 { " D" } else { "" },
+                if flags & PTE_G != 0 { " G" } else { "" })?;
+        }
+        Ok(())
+    }
+}
+
+/// number of tlb entries
+#[inline]
+pub fn tlb_entries() -> usize {
+    unsafe { tlb_size() }
+}
+
+/// read the tlb entry at index
+#[inline]
+pub fn tlb_read(index: usize) -> TlbEntry {
+    let mut entry = TlbEntry::default();
+    unsafe { tlb_read_entry(index, &mut entry); }
+    entry
+}
+
+/// read every tlb entry
+pub fn tlb_dump() -> Vec<TlbEntry> {
+    (0..tlb_entries()).map(tlb_read).collect()
 }
 
 /// invalidate a tlb item
@@ -14,16 +102,44 @@ pub fn tlb_invalidate(asid: ASID, va: VirtAddr) {
     unsafe { tlb_out(entry); }
 }
 
-/// do tlb refill
+/// invalidate all tlb items
+#[inline]
+pub fn tlb_flush() {
+    unsafe { tlb_flush_all(); }
+}
+
+/// do tlb refill. an env over its frame limit crashes instead of allocating a page passively,
+/// running out of frames kills an env to free some.
 #[no_mangle]
 pub extern "C" fn _do_tlb_refill(entries: &mut [usize; 2], va: VirtAddr, asid: ASID) {
-    cur_pgdir(|pgdir| {
-        pgdir.do_tlb_refill(entries, va, asid);
-    })
+    env_enter_kernel();
+    if cur_check_frames(va).is_err() {
+        env_crash(SIGSEGV);
+    }
+    let allocated = loop {
+        let mut result = Ok(false);
+        cur_pgdir(|pgdir| {
+            result = pgdir.do_tlb_refill(entries, va, asid);
+        });
+        match result {
+            Ok(allocated) => break allocated,
+            Err(Error::NoMem) => oom_kill(),
+            Err(_) => env_crash(SIGSEGV)
+        }
+    };
+    cur_rusage(|ru| {
+        ru.tlb_refills += 1;
+        if allocated {
+            ru.passive_allocs += 1;
+        }
+    });
+    env_leave_kernel();
 }
 
 /// do tlb modification
 #[no_mangle]
 pub extern "C" fn do_tlb_mod(tf: &mut Trapframe) {
+    env_enter_kernel();
     tf.do_tlb_mod();
+    env_leave_kernel();
 }
\ No newline at end of file
@@ -0,0 +1,284 @@
+use core::str;
+
+use crate::{device::serial::{serial_flush, serial_getc, serial_putc}, env::{env_busy, env_list, env_pgdir, env_stat, env_trapframe, envid2ind, EnvID}, exception::traps::{Trapframe, STATUS_UM}, memory::{frame::{frame_ref, frame_stats}, heap::heap_stats, mmu::*, page_table::Pte, tlb::{tlb_entries, tlb_read}}, print, println, sync::cell::UPSafeCell};
+
+/// key that enters the monitor when typed twice in a row on console, ^]
+pub const MONITOR_KEY: u8 = 0x1d;
+
+/// max length of a command line
+const MONITOR_LINE_MAX: usize = 128;
+
+const BACKSPACE: u8 = 0x08;
+const DELETE: u8 = 0x7f;
+
+/// whether the last byte received on console was a monitor key held back from tty
+static KEY_HELD: UPSafeCell<bool> = UPSafeCell::new(false);
+
+/// names of general registers
+const REG_NAMES: [&str; 32] = [
+    "zero", "at", "v0", "v1", "a0", "a1", "a2", "a3",
+    "t0", "t1", "t2", "t3", "t4", "t5", "t6", "t7",
+    "s0", "s1", "s2", "s3", "s4", "s5", "s6", "s7",
+    "t8", "t9", "k0", "k1", "gp", "sp", "s8", "ra"
+];
+
+/// what tty does with a byte received on console
+pub enum MagicKey {
+    /// pass the byte to tty
+    Pass(u8),
+    /// the held monitor key was plain input, pass it and then the byte
+    PassHeld(u8),
+    /// hold the byte back until the next one is received
+    Hold,
+    /// enter the monitor
+    Enter
+}
+
+/// look for the monitor key sequence in console input
+pub fn monitor_key(c: u8) -> MagicKey {
+    let mut held = KEY_HELD.borrow_mut();
+    match (*held, c == MONITOR_KEY) {
+        (false, false) => MagicKey::Pass(c),
+        (false, true) => {
+            *held = true;
+            MagicKey::Hold
+        }
+        (true, true) => {
+            *held = false;
+            MagicKey::Enter
+        }
+        (true, false) => {
+            *held = false;
+            MagicKey::PassHeld(c)
+        }
+    }
+}
+
+/// wait for a byte from console by polling, interrupts are off in the monitor
+fn getc() -> u8 {
+    serial_flush();
+    loop {
+        if let Some(c) = serial_getc() {
+            return c;
+        }
+    }
+}
+
+/// read a command line with echo and backspace
+fn read_line(buf: &mut [u8; MONITOR_LINE_MAX]) -> &str {
+    let mut len = 0;
+    loop {
+        match getc() {
+            b'\r' | b'\n' => break,
+            BACKSPACE | DELETE => {
+                if len > 0 {
+                    len -= 1;
+                    print!("\x08 \x08");
+                }
+            }
+            c if c.is_ascii_graphic() || c == b' ' => {
+                if len < buf.len() {
+                    buf[len] = c;
+                    len += 1;
+                    serial_putc(c);
+                }
+            }
+            _ => {}
+        }
+    }
+    println!("");
+    str::from_utf8(&buf[..len]).unwrap_or("")
+}
+
+/// parse a hex number, `0x` is optional
+fn parse_hex(s: &str) -> Option<usize> {
+    let s = s.strip_prefix("0x").unwrap_or(s);
+    usize::from_str_radix(s, 16).ok()
+}
+
+/// check that env manager and heap can be used, report if not
+fn env_usable() -> bool {
+    if env_busy() || heap_stats().is_none() {
+        println!("env manager is in use by interrupted code");
+        false
+    } else {
+        true
+    }
+}
+
+/// print a trapframe
+fn print_tf(tf: &Trapframe) {
+    for (i, reg) in tf.regs.iter().enumerate() {
+        print!("{:>4} {:08x}{}", REG_NAMES[i], reg, if i % 4 == 3 { "\n" } else { "  " });
+    }
+    println!("  hi {:08x}    lo {:08x}   epc {:08x}",  tf.hi, tf.lo, tf.cp0_epc);
+    println!("  sr {:08x} cause {:08x}  badva {:08x} {}",
+        tf.cp0_status, tf.cp0_cause, tf.cp0_badvaddr, if tf.cp0_status & STATUS_UM != 0 { "user" } else { "kernel" });
+}
+
+/// describe flags of an entry
+fn print_pte(what: &str, pte: Pte) {
+    let perm = pte.perm();
+    println!("{} {:08x} ppn {:05x}{}{}{}{}{}", what, pte.as_usize(), pte.ppn().as_usize(),
+        if perm & PTE_V != 0 { " V" } else { " -" },
+        if perm & PTE_D != 0 { " D" } else { "" },
+        if perm & PTE_G != 0 { " G" } else { "" },
+        if perm & PTE_COW != 0 { " COW" } else { "" },
+        if perm & PTE_LIBRARY != 0 { " LIB" } else { "" });
+}
+
+/// `envs`: list live envs
+fn cmd_envs() {
+    if !env_usable() {
+        return;
+    }
+    println!("envid    parent   state pri     runs    ticks pages name");
+    for envid in env_list() {
+        let st = match env_stat(envid) {
+            Ok(st) => st,
+            Err(_) => continue
+        };
+        let state = match (st.env_status, st.env_ipc_recving, st.env_sleeping) {
+            (1, _, _) => "run",
+            (_, 0, 0) => "block",
+            (_, 0, _) => "sleep",
+            _ => "ipc"
+        };
+        let name = &st.env_name[..st.env_name.iter().position(|&c| c == 0).unwrap_or(st.env_name.len())];
+        println!("{:08x} {:08x} {:<5} {:>3} {:>8} {:>8} {:>5} {}", st.env_id, st.env_parent_id, state,
+            st.env_pri, st.env_runs, st.env_ticks, st.env_pages, str::from_utf8(name).unwrap_or("?"));
+    }
+}
+
+/// `tf [envid]`: dump a trapframe, the one the monitor was entered with by default
+fn cmd_tf(arg: Option<&str>, entry_tf: Option<&Trapframe>) {
+    match (arg.map(parse_hex), entry_tf) {
+        (None, Some(tf)) => print_tf(tf),
+        (None, None) => {
+            println!("usage: tf envid");
+        }
+        (Some(None), _) => {
+            println!("bad envid");
+        }
+        (Some(Some(envid)), _) => {
+            if !env_usable() {
+                return;
+            }
+            if env_trapframe(EnvID::new(envid), |tf| print_tf(tf)).is_err() {
+                println!("no env {:x}", envid);
+            }
+        }
+    }
+}
+
+/// `walk envid va`: walk the page table of an env for an address
+fn cmd_walk(envid: Option<&str>, va: Option<&str>) {
+    let (envid, va) = match (envid.and_then(parse_hex), va.and_then(parse_hex)) {
+        (Some(envid), Some(va)) => (envid, VirtAddr::new(va)),
+        _ => {
+            println!("usage: walk envid va");
+            return;
+        }
+    };
+    if !env_usable() {
+        return;
+    }
+    let ind = match envid2ind(EnvID::new(envid), 0) {
+        Ok(ind) => ind,
+        Err(_) => {
+            println!("no env {:x}", envid);
+            return;
+        }
+    };
+    let mut entries = None;
+    env_pgdir(ind, |pgdir| entries = Some(pgdir.walk(va)));
+    let (pde, pte) = match entries {
+        Some(entries) => entries,
+        None => {
+            println!("env {:x} has no page dir", envid);
+            return;
+        }
+    };
+    println!("va {:08x} pdx {:03x} ptx {:03x}", va.as_usize(), va.pdx(), va.ptx());
+    print_pte("pde", pde);
+    match pte {
+        Some(pte) => print_pte("pte", pte),
+        None => {
+            println!("pte not present");
+        }
+    }
+    if let (Some(pte), Some((nframes, _))) = (pte, frame_stats()) {
+        if pte.valid() && pte.ppn().as_usize() < nframes {
+            println!("pa {:08x} ref {}", pte.addr().as_usize() + va.page_offset(), frame_ref(pte.ppn()));
+        }
+    }
+}
+
+/// `mem`: frame allocator and heap usage
+fn cmd_mem() {
+    match frame_stats() {
+        Some((nframes, free)) => {
+            println!("frames: {} total, {} free, {} used", nframes, free, nframes - free);
+        }
+        None => {
+            println!("frames: allocator in use");
+        }
+    }
+    match heap_stats() {
+        Some((user, actual, total)) => {
+            println!("heap: {} bytes total, {} allocated, {} requested", total, actual, user);
+        }
+        None => {
+            println!("heap: allocator in use");
+        }
+    }
+}
+
+/// `tlb [all]`: dump valid tlb entries, or every entry
+fn cmd_tlb(arg: Option<&str>) {
+    let all = arg == Some("all");
+    for index in 0..tlb_entries() {
+        let entry = tlb_read(index);
+        if all || entry.valid() {
+            println!("{:>2} {}", index, entry);
+        }
+    }
+}
+
+/// `help`: list commands
+fn cmd_help() {
+    println!("envs            list envs");
+    println!("tf [envid]      dump trapframe of env, or the one the monitor was entered with");
+    println!("walk envid va   walk page table of env for va");
+    println!("mem             frame and heap usage");
+    println!("tlb [all]       dump valid tlb entries, or all of them");
+    println!("c, continue     leave the monitor");
+}
+
+/// run the monitor on console until told to continue. tf is the frame of the trap that entered it,
+/// a monitor that can't resume never returns.
+pub fn monitor(reason: &str, tf: Option<&Trapframe>, resume: bool) {
+    let mut buf = [0; MONITOR_LINE_MAX];
+    println!("\nkernel monitor: {}, type help for commands", reason);
+    loop {
+        print!("K> ");
+        let mut words = read_line(&mut buf).split_whitespace();
+        match words.next() {
+            None => {}
+            Some("help") => cmd_help(),
+            Some("envs") => cmd_envs(),
+            Some("tf") => cmd_tf(words.next(), tf),
+            Some("walk") => cmd_walk(words.next(), words.next()),
+            Some("mem") => cmd_mem(),
+            Some("tlb") => cmd_tlb(words.next()),
+            Some("c" | "continue") if resume => break,
+            Some("c" | "continue") => {
+                println!("can't resume");
+            }
+            Some(cmd) => {
+                println!("unknown command {}", cmd);
+            }
+        }
+    }
+    serial_flush();
+}
@@ -1,10 +1,17 @@
-use core::panic::PanicInfo;
+use core::{mem::replace, panic::PanicInfo};
 
-use crate::println;
+use crate::{device::serial::serial_flush, monitor::monitor, println, sync::cell::UPSafeCell};
+
+/// set by the first panic, a panic in the monitor just stops
+static PANICKED: UPSafeCell<bool> = UPSafeCell::new(false);
 
 /// kernel panic implementation.
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
     println!("{}", info);
+    serial_flush();
+    if !replace(&mut *PANICKED.borrow_mut(), true) {
+        monitor("panic", None, false);
+    }
     loop {}
 }
\ No newline at end of file
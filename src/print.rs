@@ -1,30 +1,15 @@
-use core::{fmt::{self, Write}, ptr::{read_volatile, write_volatile}};
+use core::fmt::{self, Write};
 
-use crate::memory::mmu::KSEG1;
-use crate::device::malta;
-
-
-const STATE_ADDR: *const u8 = (KSEG1 + malta::MALTA_SERIAL_LSR) as *const u8;
-const DATA_ADDR: *mut u8 = (KSEG1 + malta::MALTA_SERIAL_DATA) as *mut u8;
+use crate::device::serial::{serial_getc, serial_putc};
 
 /// put char to malta
 pub fn printcharc(byte: u8) {
-    if byte == b'\n' {
-        printcharc(b'\r');
-    }
-    
-    while unsafe { read_volatile(STATE_ADDR) } & malta::MALTA_SERIAL_THR_EMPTY == 0 {}
-    unsafe {write_volatile(DATA_ADDR, byte)};
+    serial_putc(byte);
 }
 
 /// get char from malta
 pub fn scancharc() -> u8 {
-    if unsafe {
-        read_volatile(STATE_ADDR) & malta::MALTA_SERIAL_DATA_READY
-    } != 0 {
-        return unsafe { read_volatile(DATA_ADDR) };
-    }
-    return 0;
+    serial_getc().unwrap_or(0)
 }
 
 // Stdout to serial device.
@@ -1,2 +1,4 @@
 /// sync cell
-pub mod cell;
\ No newline at end of file
+pub mod cell;
+/// wait queue
+pub mod wait_queue;
\ No newline at end of file
@@ -19,4 +19,9 @@ impl<T> UPSafeCell<T> {
     pub fn borrow_mut(&self) -> RefMut<'_, T>{
         self.inner.borrow_mut()
     }
+    /// borrow a ref mut, None if it is already borrowed
+    #[inline]
+    pub fn try_borrow_mut(&self) -> Option<RefMut<'_, T>> {
+        self.inner.try_borrow_mut().ok()
+    }
 }
\ No newline at end of file
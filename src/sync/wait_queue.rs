@@ -0,0 +1,39 @@
+use alloc::vec::Vec;
+
+use crate::env::{env_sleep, env_sleeping, env_wakeup, get_cur_env_id, EnvID};
+
+/// queue of envs blocked in a syscall, each with the sequence number of its sleep
+pub struct WaitQueue {
+    waiters: Vec<(EnvID, usize)>
+}
+
+impl WaitQueue {
+    /// create an empty wait queue
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            waiters: Vec::new()
+        }
+    }
+    /// check if no env is waiting
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.waiters.iter().all(|&(envid, seq)| !env_sleeping(envid, seq))
+    }
+    /// block current env on this queue, the syscall is restarted when woken up.
+    /// waiters woken by a note or destroyed since they slept are removed.
+    /// caller should release its borrows and call env_sched afterwards.
+    pub fn sleep(&mut self) {
+        if let Some(envid) = get_cur_env_id() {
+            self.waiters.retain(|&(envid, seq)| env_sleeping(envid, seq));
+            let seq = env_sleep();
+            self.waiters.push((envid, seq));
+        }
+    }
+    /// wake up all waiting envs
+    pub fn wake_all(&mut self) {
+        for (envid, seq) in self.waiters.drain(..) {
+            env_wakeup(envid, seq);
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use core::{any::type_name, panic::PanicInfo};
+
+use crate::{device::{halt, serial::serial_flush}, env::ASID, memory::{frame::{frame_alloc, frame_decref, frame_incref}, mmu::{PhysPageNum, UTOP}, page_table::PageTable}, print, println, sync::cell::UPSafeCell};
+
+/// progress of the test run, read by the panic handler when a test fails
+struct TestRun {
+    total: usize,
+    passed: usize,
+    current: &'static str
+}
+
+static TEST_RUN: UPSafeCell<TestRun> = UPSafeCell::new(TestRun { total: 0, passed: 0, current: "" });
+
+/// a kernel test case, it fails by panicking
+pub trait Testable {
+    fn run(&self);
+}
+
+impl<T: Fn()> Testable for T {
+    /// run the test between its `test name ... ok` line
+    fn run(&self) {
+        let name = type_name::<T>();
+        let name = name.split_once("::").map_or(name, |(_, rest)| rest);
+        TEST_RUN.borrow_mut().current = name;
+        print!("test {} ... ", name);
+        self();
+        println!("ok");
+        TEST_RUN.borrow_mut().passed += 1;
+    }
+}
+
+/// print the summary line and exit qemu with status 1 if any test failed.
+/// the status goes through a UHI exit semihosting call, which needs qemu started with `-semihosting`
+fn finish(passed: usize, failed: usize) -> ! {
+    println!("test result: {}. {} passed; {} failed", if failed == 0 { "ok" } else { "FAILED" }, passed, failed);
+    serial_flush();
+    // UHI operation number in t9, exit status in a0
+    #[cfg(target_arch = "mips")]
+    unsafe { core::arch::asm!("sdbbp 1", in("$25") 1, in("$4") (failed != 0) as usize); }
+    halt();
+}
+
+/// run every `#[test_case]`, called by `test_main` after memory is initialized
+pub fn test_runner(tests: &[&dyn Testable]) {
+    println!("running {} tests", tests.len());
+    TEST_RUN.borrow_mut().total = tests.len();
+    for test in tests {
+        test.run();
+    }
+    let passed = TEST_RUN.borrow_mut().passed;
+    finish(passed, 0);
+}
+
+/// a failed assertion ends the run, the remaining tests are not run
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    println!("FAILED");
+    println!("{}", info);
+    let (total, passed, current) = match TEST_RUN.try_borrow_mut() {
+        Some(run) => (run.total, run.passed, run.current),
+        None => (0, 0, "?")
+    };
+    println!("failed test: {}, {} tests not run", current, total.saturating_sub(passed + 1));
+    finish(passed, 1);
+}
+
+/// a page dir for tests, its page tables and mappings below UTOP are dropped with it
+pub struct TestPgdir {
+    ppn: PhysPageNum,
+    pub asid: ASID
+}
+
+impl TestPgdir {
+    /// allocate an empty page dir
+    pub fn new() -> Self {
+        let ppn = frame_alloc().unwrap();
+        frame_incref(ppn);
+        Self { ppn, asid: ASID::zero() }
+    }
+
+    /// the page table
+    pub fn pgdir(&mut self) -> &mut PageTable {
+        unsafe { &mut *self.ppn.into_kva().as_mut_ptr::<PageTable>() }
+    }
+}
+
+impl Drop for TestPgdir {
+    fn drop(&mut self) {
+        let (ppn, asid) = (self.ppn, self.asid);
+        let pgdir = self.pgdir();
+        for (va, _) in pgdir.mapped(UTOP) {
+            pgdir.remove(asid, va);
+        }
+        for pdeno in 0..UTOP.pdx() {
+            let pde = pgdir.get_entry(pdeno);
+            if pde.valid() {
+                frame_decref(pde.ppn());
+            }
+        }
+        frame_decref(ppn);
+    }
+}
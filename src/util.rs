@@ -6,6 +6,8 @@ pub mod linked_list;
 pub mod bitops;
 /// elf utils
 pub mod elf;
+/// ring buffer
+pub mod ring_buffer;
 
 /// try macro in rust
 #[macro_export]
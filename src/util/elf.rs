@@ -30,6 +30,32 @@ pub const ELFMAG2: u8 = b'L';
 pub const EI_MAG3: usize = 3;
 /// ELF Constant
 pub const ELFMAG3: u8 = b'F';
+/// ELF Constant
+pub const EI_CLASS: usize = 4;
+/// ELF Constant
+pub const ELFCLASS32: u8 = 1;
+/// ELF Constant
+pub const EI_DATA: usize = 5;
+/// ELF Constant
+pub const ELFDATA2LSB: u8 = 1;
+/// ELF Constant
+pub const EI_VERSION: usize = 6;
+/// ELF Constant
+pub const EV_CURRENT: u8 = 1;
+
+/// ELF Constant
+pub const ET_CORE: u16 = 4;	     /* Core file */
+/// ELF Constant
+pub const EM_MIPS: u16 = 8;	     /* MIPS R3000 */
+/// ELF Constant
+pub const EF_MIPS_ABI_O32: u32 = 0x00001000;
+/// ELF Constant
+pub const EF_MIPS_ARCH_32R2: u32 = 0x70000000;
+
+/// ELF Constant
+pub const NT_PRSTATUS: u32 = 1;	     /* Registers of a process */
+/// ELF Constant
+pub const NT_PRPSINFO: u32 = 3;	     /* Command line of a process */
 
 /// Elf ehdr
 #[repr(C)]
@@ -64,6 +90,14 @@ pub struct Elf32Phdr {
     p_align: Elf32Word
 }
 
+/// Elf note header
+#[repr(C)]
+pub struct Elf32Nhdr {
+    n_namesz: Elf32Word,
+    n_descsz: Elf32Word,
+    n_type: Elf32Word
+}
+
 /// Phdr iterator
 pub struct PhdrIterator<'a> {
     ehdr: &'a Elf32Ehdr,
@@ -79,7 +113,7 @@ pub const PT_DYNAMIC: usize = 2;	     /* Dynamic linking information */
 /// ELF Constant
 pub const PT_INTERP: usize = 3;	     /* Program interpreter */
 /// ELF Constant
-pub const PT_NOTE: usize = 4;	     /* Auxiliary information */
+pub const PT_NOTE: u32 = 4;	     /* Auxiliary information */
 /// ELF Constant
 pub const PT_SHLIB: usize = 5;	     /* Reserved */
 /// ELF Constant
@@ -173,4 +207,59 @@ impl Elf32Ehdr {
             ind: 0
         }
     }
-}
\ No newline at end of file
+
+    /// header of a core file with phnum program headers following it
+    pub fn new_core(phnum: usize) -> Self {
+        let mut e_ident = [0; EI_NIDENT];
+        e_ident[EI_MAG0] = ELFMAG0;
+        e_ident[EI_MAG1] = ELFMAG1;
+        e_ident[EI_MAG2] = ELFMAG2;
+        e_ident[EI_MAG3] = ELFMAG3;
+        e_ident[EI_CLASS] = ELFCLASS32;
+        e_ident[EI_DATA] = ELFDATA2LSB;
+        e_ident[EI_VERSION] = EV_CURRENT;
+        Self {
+            e_ident,
+            e_type: ET_CORE,
+            e_machine: EM_MIPS,
+            e_version: EV_CURRENT as Elf32Word,
+            e_entry: 0,
+            e_phoff: size_of::<Elf32Ehdr>() as Elf32Off,
+            e_shoff: 0,
+            e_flags: EF_MIPS_ARCH_32R2 | EF_MIPS_ABI_O32,
+            e_ehsize: size_of::<Elf32Ehdr>() as Elf32Half,
+            e_phentsize: size_of::<Elf32Phdr>() as Elf32Half,
+            e_phnum: phnum as Elf32Half,
+            e_shentsize: 0,
+            e_shnum: 0,
+            e_shstrndx: 0
+        }
+    }
+}
+
+impl Elf32Phdr {
+    /// program header of a segment backed by filesz bytes at offset
+    pub fn new(p_type: u32, offset: usize, va: usize, filesz: usize, memsz: usize, flags: u32) -> Self {
+        Self {
+            p_type,
+            p_offset: offset as Elf32Off,
+            p_vaddr: va as Elf32Addr,
+            p_paddr: 0,
+            p_filesz: filesz as Elf32Word,
+            p_memsz: memsz as Elf32Word,
+            p_flags: flags,
+            p_align: if p_type == PT_LOAD { PAGE_SIZE as Elf32Word } else { 4 }
+        }
+    }
+}
+
+impl Elf32Nhdr {
+    /// note header of descsz bytes with a name of namesz bytes including nul
+    pub fn new(namesz: usize, descsz: usize, n_type: u32) -> Self {
+        Self {
+            n_namesz: namesz as Elf32Word,
+            n_descsz: descsz as Elf32Word,
+            n_type
+        }
+    }
+}
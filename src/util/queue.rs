@@ -41,6 +41,32 @@ impl IndexLink {
         self.le_prev[n + 1] = Some(n);
     }
 
+    /// grow to hold n elements, the two sentinels are moved to the new end
+    pub fn resize(&mut self, n: usize) {
+        if n <= self.n {
+            return;
+        }
+        let (old_head, old_tail) = (self.n, self.n + 1);
+        let first = self.le_next[old_head].unwrap();
+        let last = self.le_prev[old_tail].unwrap();
+        self.le_next.resize(n + 2, None);
+        self.le_prev.resize(n + 2, None);
+        for i in [old_head, old_tail] {
+            self.le_next[i] = None;
+            self.le_prev[i] = None;
+        }
+        self.n = n;
+        if first == old_tail {
+            self.le_next[n] = Some(n + 1);
+            self.le_prev[n + 1] = Some(n);
+        } else {
+            self.le_next[n] = Some(first);
+            self.le_prev[first] = Some(n);
+            self.le_prev[n + 1] = Some(last);
+            self.le_next[last] = Some(n + 1);
+        }
+    }
+
     /// check if empty
     #[inline]
     pub fn is_empty(&self) -> bool {
@@ -141,4 +167,62 @@ impl<'a> Iterator for IndexIterator<'a> {
             None => None
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::IndexLink;
+
+    #[test_case]
+    fn insert_order() {
+        let mut link = IndexLink::new();
+        link.init(4);
+        assert!(link.is_empty());
+        link.insert_tail(1);
+        link.insert_tail(2);
+        link.insert_head(0);
+        assert_eq!(link.iter().collect::<Vec<_>>(), [0, 1, 2]);
+        assert_eq!(link.first(), Some(0));
+        assert_eq!(link.len(), 3);
+    }
+
+    #[test_case]
+    fn remove() {
+        let mut link = IndexLink::new();
+        link.init(4);
+        for i in 0..4 {
+            link.insert_tail(i);
+        }
+        link.remove(1);
+        link.remove(3);
+        assert_eq!(link.iter().collect::<Vec<_>>(), [0, 2]);
+        assert_eq!(link.len(), 2);
+        link.remove(0);
+        link.remove(2);
+        assert!(link.is_empty());
+    }
+
+    #[test_case]
+    fn resize_keeps_order() {
+        let mut link = IndexLink::new();
+        link.init(2);
+        link.insert_tail(1);
+        link.insert_tail(0);
+        link.resize(5);
+        link.insert_tail(4);
+        assert_eq!(link.iter().collect::<Vec<_>>(), [1, 0, 4]);
+        assert_eq!(link.first(), Some(1));
+    }
+
+    #[test_case]
+    fn resize_empty() {
+        let mut link = IndexLink::new();
+        link.init(2);
+        link.resize(4);
+        assert!(link.is_empty());
+        link.insert_head(3);
+        assert_eq!(link.iter().collect::<Vec<_>>(), [3]);
+    }
+}
@@ -0,0 +1,100 @@
+/// fixed size ring buffer
+pub struct RingBuffer<T: Copy, const N: usize> {
+    buf: [T; N],
+    head: usize,
+    len: usize,
+}
+
+impl<T: Copy, const N: usize> RingBuffer<T, N> {
+    /// create a new ring buffer, filled with given value
+    #[inline]
+    pub const fn new(fill: T) -> Self {
+        Self {
+            buf: [fill; N],
+            head: 0,
+            len: 0
+        }
+    }
+    /// check if empty
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// check if full
+    #[inline]
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+    /// number of elements
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    /// capacity of buffer
+    #[inline]
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+    /// push an element to tail, fail if full
+    #[inline]
+    pub fn push(&mut self, v: T) -> Result<(), T> {
+        if self.is_full() {
+            return Err(v);
+        }
+        self.buf[(self.head + self.len) % N] = v;
+        self.len += 1;
+        Ok(())
+    }
+    /// push an element to tail, drop the oldest one if full
+    #[inline]
+    pub fn push_overwrite(&mut self, v: T) {
+        if self.is_full() {
+            self.pop();
+        }
+        let _ = self.push(v);
+    }
+    /// pop an element from head
+    #[inline]
+    pub fn pop(&mut self) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+        let v = self.buf[self.head];
+        self.head = (self.head + 1) % N;
+        self.len -= 1;
+        Some(v)
+    }
+    /// pop the newest element from tail
+    #[inline]
+    pub fn pop_back(&mut self) -> Option<T> {
+        if self.is_empty() {
+            return None;
+        }
+        self.len -= 1;
+        Some(self.buf[(self.head + self.len) % N])
+    }
+    /// peek the head element
+    #[inline]
+    pub fn peek(&self) -> Option<T> {
+        if self.is_empty() {
+            None
+        } else {
+            Some(self.buf[self.head])
+        }
+    }
+    /// get the i-th element from head
+    #[inline]
+    pub fn get(&self, i: usize) -> Option<T> {
+        if i >= self.len {
+            None
+        } else {
+            Some(self.buf[(self.head + i) % N])
+        }
+    }
+    /// remove all elements
+    #[inline]
+    pub fn clear(&mut self) {
+        self.head = 0;
+        self.len = 0;
+    }
+}
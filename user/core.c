@@ -0,0 +1,90 @@
+#include <lib.h>
+
+char buf[PAGE_SIZE];
+
+// Write the held core dump to path and drop it from the kernel. Return the bytes written, the
+// file is not created if the core can't be read.
+static int save(const char *path) {
+	u_int off = 0;
+	int fd, n, r;
+
+	if ((n = syscall_core_read(buf, 0, sizeof buf)) < 0) {
+		return n;
+	}
+	if ((fd = open(path, O_WRONLY | O_CREAT | O_TRUNC)) < 0) {
+		user_panic("can't open %s: %d", path, fd);
+	}
+	while (n > 0) {
+		if ((r = write(fd, buf, n)) != n) {
+			user_panic("write error on %s: %d", path, r);
+		}
+		off += n;
+		n = syscall_core_read(buf, off, sizeof buf);
+	}
+	close(fd);
+	if (n < 0) {
+		return n;
+	}
+	syscall_core_clear();
+	return off;
+}
+
+// Name the core file of an env /core.<envid>.
+static void core_path(char *path, u_int envid) {
+	strcpy(path, "/core.");
+	for (int i = 0; i < 8; i++) {
+		path[6 + i] = "0123456789abcdef"[(envid >> (28 - 4 * i)) & 0xf];
+	}
+	path[14] = 0;
+}
+
+// Save the core dump of the latest crashed env to a file, then drop it from the kernel.
+// Only the parent of the crashed env or the core supervisor may read it. With -d, become the
+// supervisor and save the core of every env that crashes to /core.<envid>.
+// Inspect a file with 'gdb-multiarch <program> <file>'.
+int main(int argc, char **argv) {
+	struct CoreInfo info;
+	char path[16];
+	int daemon = 0, n, r;
+
+	ARGBEGIN {
+	case 'd':
+		daemon = 1;
+		break;
+	default:
+		printf("usage: core [-d] [file]\n");
+		return 1;
+	}
+	ARGEND
+
+	if (daemon) {
+		for (;;) {
+			if ((r = syscall_core_wait()) < 0) {
+				user_panic("core_wait: %d", r);
+			}
+			if (syscall_core_info(&info) < 0) {
+				continue;
+			}
+			core_path(path, info.core_envid);
+			if ((n = save(path)) < 0) {
+				debugf("core: core_read: %d\n", n);
+				continue;
+			}
+			debugf("core of %08x (signal %d) saved to %s, %d bytes\n", info.core_envid,
+			       info.core_signal, path, n);
+		}
+	}
+
+	const char *file = argc > 0 ? argv[0] : "/core";
+	if ((r = syscall_core_info(&info)) < 0) {
+		printf("core: %s\n", r == -E_NOT_FOUND ? "no core dump" : "not permitted to read core");
+		return 1;
+	}
+	if ((n = save(file)) < 0) {
+		printf("core: can't read core of %08x: %d\n", info.core_envid, n);
+		return 1;
+	}
+	printf("core of %08x (signal %d) saved to %s, %d bytes\n", info.core_envid,
+	       info.core_signal, file, n);
+	return 0;
+}
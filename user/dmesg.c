@@ -0,0 +1,27 @@
+#include <lib.h>
+
+// size of the kernel log buffer
+#define LOG_BUF_LEN 16384
+
+char buf[LOG_BUF_LEN];
+
+// Print the kernel log, each line is '[tick] level module: message'.
+int main(int argc, char **argv) {
+	int n;
+
+	if ((n = syscall_klog_read(buf, sizeof buf)) < 0) {
+		user_panic("klog_read: %d", n);
+	}
+	// the oldest line may have been cut when the buffer wrapped
+	int start = 0;
+	if (n == sizeof buf) {
+		while (start < n && buf[start] != '\n') {
+			start++;
+		}
+		start++;
+	}
+	if (start < n && (n = write(1, buf + start, n - start)) < 0) {
+		user_panic("write: %d", n);
+	}
+	return 0;
+}
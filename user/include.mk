@@ -39,6 +39,13 @@ USERAPPS     := num.b  \
 		testfdsharing.b \
 		pingpong.b \
 		init.b \
-		shmtest.b
+		shmtest.b \
+		ps.b \
+		core.b \
+		strace.b \
+		dmesg.b \
+		prof.b \
+		tlb.b \
+		memcheck.b
 
 USERLIB := $(addprefix lib/, $(USERLIB)) $(wildcard ../lib/*.o)
\ No newline at end of file
@@ -10,13 +10,104 @@
 
 #define vpt ((volatile Pte *)UVPT)
 #define vpd ((volatile Pde *)(UVPT + (PDX(UVPT) << PGSHIFT)))
-#define envs ((volatile struct Env *)UENVS)
+#define envs ((volatile struct EnvInfo *)UENVS)
 #define pages ((volatile struct Page *)UPAGES)
 
+// wall-clock time since unix epoch
+struct TimeVal {
+	u_int sec;
+	u_int usec;
+};
+
+#define RUSAGE_SELF 0
+#define RUSAGE_CHILDREN 1
+
+#define RLIMIT_FRAMES 0
+#define RLIMIT_CHILDREN 1
+#define RLIMIT_SHM 2
+#define RLIMIT_CPU 3
+#define RLIM_INFINITY 0x7fffffff
+
+// ptrace requests, see syscall_ptrace
+#define PT_ATTACH 0
+#define PT_DETACH 1
+#define PT_READ 2
+#define PT_WRITE 3
+#define PT_GETREGS 4
+#define PT_SETREGS 5
+#define PT_CONT 6
+#define PT_STEP 7
+#define PT_WAIT 8
+#define PT_STOP 9
+
+// stop reasons returned by PT_WAIT
+#define STOP_ATTACH 1
+#define STOP_BREAK 2
+#define STOP_STEP 3
+#define STOP_REQUEST 4
+
+// core dump of the latest crashed env, see syscall_core_info
+struct CoreInfo {
+	u_int core_envid;  // id of the crashed env
+	u_int core_signal; // signal of the fault
+	u_int core_size;   // size of the ELF core file in bytes
+};
+
+// syscall tracing flags, see syscall_strace
+#define STRACE_RECORD 1	 // record into the kernel buffer read by syscall_strace_read
+#define STRACE_CONSOLE 2 // print on console
+#define STRACE_FOLLOW 4	 // forked envs inherit the flags
+
+// a traced syscall that has returned
+struct SyscallRecord {
+	u_int sr_envid;
+	u_int sr_sysno;
+	u_int sr_args[5];
+	int sr_ret;
+	u_int sr_tick; // uptime in timer ticks
+};
+
+// where a timer interrupt found the cpu, see syscall_prof_read
+struct ProfSample {
+	u_int ps_epc;
+	u_int ps_envid; // zero when idle
+	u_int ps_asid;
+	u_int ps_kernel; // nonzero if taken in kernel mode
+};
+
+// a tlb entry as read by tlbr, see syscall_tlb_read
+struct TlbEntry {
+	u_int te_hi; // vpn2 and asid
+	u_int te_lo[2]; // EntryLo of the even and odd page
+	u_int te_pagemask;
+};
+
+// frame ref count check result, see syscall_memcheck
+struct MemCheck {
+	u_int mc_frames;
+	u_int mc_used; // frames something holds a ref on
+	u_int mc_mismatches; // ref count differs from the refs held
+	u_int mc_leaks; // held by nothing but not free
+	u_int mc_double_maps; // page tables held twice or used as something else
+	u_int mc_wild; // ptes past the last frame
+};
+
+// resource usage, see syscall_getrusage
+struct Rusage {
+	u_int ru_utime;		     // ticks in user mode
+	u_int ru_stime;		     // ticks in kernel mode
+	u_int ru_maxrss;	     // peak resident pages
+	u_int ru_tlb_refills;	     // tlb refill exceptions
+	u_int ru_passive_allocs;     // pages allocated on tlb refill
+	u_int ru_ipc_sends;	     // successful ipc sends
+	u_int ru_ipc_recvs;	     // ipc receives
+	u_int ru_syscalls[MAX_SYSNO]; // syscall counts by id
+};
+
 // libos
 void exit(void) __attribute__((noreturn));
 
-extern volatile struct Env *env;
+extern volatile struct EnvInfo *env;
 
 #define USED(x) (void)(x)
 
@@ -76,6 +167,41 @@ void syscall_semopen(int, int);
 int syscall_semwait(int);
 void syscall_sempost(int);
 void syscall_semkill(int);
+int syscall_console_read(void *, u_int);
+int syscall_tty_ioctl(u_int cmd, u_int arg);
+int syscall_env_notify(u_int envid, u_int note);
+int syscall_set_notify_entry(u_int envid, void (*func)(u_int, struct Trapframe *));
+int syscall_time_of_day(struct TimeVal *tv);
+int syscall_set_time_of_day(const struct TimeVal *tv);
+int syscall_get_trapframe(u_int envid, struct Trapframe *tf);
+int syscall_env_list(u_int *ids, u_int max);
+int syscall_env_stat(u_int envid, struct EnvStat *stat);
+int syscall_getrusage(u_int who, struct Rusage *ru);
+int syscall_get_rlimit(u_int envid, u_int res);
+int syscall_set_rlimit(u_int envid, u_int res, u_int value);
+int syscall_set_cmdline(u_int envid, const char *buf, u_int len);
+int syscall_get_cmdline(u_int envid, char *buf, u_int len);
+int syscall_set_pgid(u_int envid, u_int pgid);
+int syscall_get_pgid(u_int envid);
+int syscall_set_sid(void);
+int syscall_get_sid(u_int envid);
+int syscall_group_notify(u_int pgid, u_int note);
+int syscall_group_destroy(u_int pgid);
+int syscall_env_destroy_tree(u_int envid);
+int syscall_set_teardown(u_int envid, u_int on);
+int syscall_ptrace(u_int req, u_int envid, void *addr, void *buf, u_int len);
+int syscall_core_info(struct CoreInfo *info);
+int syscall_core_read(void *buf, u_int offset, u_int len);
+int syscall_core_clear(void);
+int syscall_strace(u_int envid, u_int flags);
+int syscall_strace_read(struct SyscallRecord *buf, u_int max);
+int syscall_klog_read(char *buf, u_int len);
+int syscall_prof_start(u_int envid);
+int syscall_prof_stop(void);
+int syscall_prof_read(struct ProfSample *buf, u_int max);
+int syscall_tlb_read(struct TlbEntry *buf, u_int max);
+int syscall_memcheck(struct MemCheck *buf);
+int syscall_core_wait(void);
 // ipc.c
 void ipc_send(u_int whom, u_int val, const void *srcva, u_int perm);
 u_int ipc_recv(u_int *whom, void *dstva, u_int *perm);
@@ -132,6 +258,22 @@ int sync(void);
 			user_panic("assertion failed: %s", #x);                                    \
 	} while (0)
 
+// tty flags
+#define TTY_ICANON 0x1 /* line editing */
+#define TTY_ECHO 0x2   /* echo input */
+#define TTY_ISIG 0x4   /* ^C and ^Z send notifications */
+
+// tty ioctl commands
+#define TTY_GET_FLAGS 0
+#define TTY_SET_FLAGS 1
+#define TTY_GET_FG 2 /* foreground process group */
+#define TTY_SET_FG 3
+
+// env notifications
+#define NOTE_INT 1
+#define NOTE_STOP 2
+#define NOTE_CONT 3
+
 // File open modes
 #define O_RDONLY 0x0000	 /* open for reading only */
 #define O_WRONLY 0x0001	 /* open for writing only */
@@ -51,6 +51,10 @@ int main(int argc, char **argv) {
 		user_panic("dup: %d", r);
 	}
 
+	// the core supervisor saves the core of every env that crashes
+	if ((r = spawnl("core.b", "core", "-d", NULL)) < 0) {
+		debugf("init: spawn core: %d\n", r);
+	}
 	while (1) {
 		debugf("init: starting sh\n");
 		r = spawnl("sh.b", "sh", NULL);
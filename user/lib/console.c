@@ -41,29 +41,11 @@ int opencons(void) {
 }
 
 int cons_read(struct Fd *fd, void *vbuf, u_int n, u_int offset) {
-	int c;
-
 	if (n == 0) {
 		return 0;
 	}
-
-	while ((c = syscall_cgetc()) == 0) {
-		syscall_yield();
-	}
-
-	if (c != '\r') {
-		debugf("%c", c);
-	} else {
-		debugf("\n");
-	}
-	if (c < 0) {
-		return c;
-	}
-	if (c == 0x04) { // ctl-d is eof
-		return 0;
-	}
-	*(char *)vbuf = c;
-	return 1;
+	// line editing, echo and ctl-d eof are handled by the kernel tty
+	return syscall_console_read(vbuf, n);
 }
 
 int cons_write(struct Fd *fd, const void *buf, u_int n, u_int offset) {
@@ -113,12 +113,10 @@ static void duppage(u_int envid, u_int vpn) {
 int fork(void) {
 	u_int child;
 	u_int i;
-	extern volatile struct Env *env;
+	extern volatile struct EnvInfo *env;
 
-	/* Step 1: Set our TLB Mod user exception entry to 'cow_entry' if not done yet. */
-	if (env->env_user_tlb_mod_entry != (u_int)cow_entry) {
-		try(syscall_set_tlb_mod_entry(0, cow_entry));
-	}
+	/* Step 1: Set our TLB Mod user exception entry to 'cow_entry'. */
+	try(syscall_set_tlb_mod_entry(0, cow_entry));
 
 	/* Step 2: Create a child env that's not ready to be scheduled. */
 	// Hint: 'env' should always point to the current env itself, so we should fix it to the
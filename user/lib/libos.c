@@ -14,7 +14,7 @@ void exit(void) {
 	user_panic("unreachable code");
 }
 
-volatile struct Env *env;
+volatile struct EnvInfo *env;
 extern int main(int, char **);
 
 void libmain(int argc, char **argv) {
@@ -101,6 +101,23 @@ static int spawn_mapper(void *data, u_long va, size_t offset, u_int perm, const
 	return 0;
 }
 
+// Record 'argv' as the command line of 'child', nul separated and truncated to
+// ENV_CMDLINE_MAX bytes.
+static int set_cmdline(u_int child, char **argv) {
+	char buf[ENV_CMDLINE_MAX];
+	u_int len = 0;
+
+	for (int i = 0; argv[i]; i++) {
+		u_int n = strlen(argv[i]) + 1;
+		if (len + n > sizeof(buf)) {
+			break;
+		}
+		memcpy(buf + len, argv[i], n);
+		len += n;
+	}
+	return syscall_set_cmdline(child, buf, len);
+}
+
 /* Note:
  *   This function involves loading executable code to memory. After the completion of load
  *   procedures, D-cache and I-cache writeback/invalidation MUST be performed to maintain cache
@@ -142,6 +159,9 @@ int spawn(char *prog, char **argv) {
 		r = child;
 		goto err;
 	}
+	if ((r = set_cmdline(child, argv)) < 0) {
+		goto err1;
+	}
 	// Step 4: Use 'init_stack(child, argv, &sp)' to initialize the stack of the child.
 	// 'goto err1' if that fails.
 	u_int sp;
@@ -187,7 +207,10 @@ int spawn(char *prog, char **argv) {
 	}
 	close(fd);
 
-	struct Trapframe tf = envs[ENVX(child)].env_tf;
+	struct Trapframe tf;
+	if ((r = syscall_get_trapframe(child, &tf)) != 0) {
+		goto err2;
+	}
 	tf.cp0_epc = entrypoint;
 	tf.regs[29] = sp;
 	if ((r = syscall_set_trapframe(child, &tf)) != 0) {
@@ -105,4 +105,144 @@ void syscall_sempost(int id) {
 
 void syscall_semkill(int id) {
 	msyscall(SYS_semkill, id);
-}
\ No newline at end of file
+}
+
+int syscall_console_read(void *buf, u_int len) {
+	return msyscall(SYS_console_read, buf, len);
+}
+
+int syscall_tty_ioctl(u_int cmd, u_int arg) {
+	return msyscall(SYS_tty_ioctl, cmd, arg);
+}
+
+int syscall_env_notify(u_int envid, u_int note) {
+	return msyscall(SYS_env_notify, envid, note);
+}
+
+int syscall_set_notify_entry(u_int envid, void (*func)(u_int, struct Trapframe *)) {
+	return msyscall(SYS_set_notify_entry, envid, func);
+}
+
+int syscall_time_of_day(struct TimeVal *tv) {
+	return msyscall(SYS_time_of_day, tv);
+}
+
+int syscall_set_time_of_day(const struct TimeVal *tv) {
+	return msyscall(SYS_set_time_of_day, tv);
+}
+
+int syscall_get_trapframe(u_int envid, struct Trapframe *tf) {
+	return msyscall(SYS_get_trapframe, envid, tf);
+}
+
+int syscall_env_list(u_int *ids, u_int max) {
+	return msyscall(SYS_env_list, ids, max);
+}
+
+int syscall_env_stat(u_int envid, struct EnvStat *stat) {
+	return msyscall(SYS_env_stat, envid, stat);
+}
+
+int syscall_getrusage(u_int who, struct Rusage *ru) {
+	return msyscall(SYS_getrusage, who, ru);
+}
+
+int syscall_get_rlimit(u_int envid, u_int res) {
+	return msyscall(SYS_get_rlimit, envid, res);
+}
+
+int syscall_set_rlimit(u_int envid, u_int res, u_int value) {
+	return msyscall(SYS_set_rlimit, envid, res, value);
+}
+
+int syscall_set_cmdline(u_int envid, const char *buf, u_int len) {
+	return msyscall(SYS_set_cmdline, envid, buf, len);
+}
+
+int syscall_get_cmdline(u_int envid, char *buf, u_int len) {
+	return msyscall(SYS_get_cmdline, envid, buf, len);
+}
+
+int syscall_set_pgid(u_int envid, u_int pgid) {
+	return msyscall(SYS_set_pgid, envid, pgid);
+}
+
+int syscall_get_pgid(u_int envid) {
+	return msyscall(SYS_get_pgid, envid);
+}
+
+int syscall_set_sid(void) {
+	return msyscall(SYS_set_sid);
+}
+
+int syscall_get_sid(u_int envid) {
+	return msyscall(SYS_get_sid, envid);
+}
+
+int syscall_group_notify(u_int pgid, u_int note) {
+	return msyscall(SYS_group_notify, pgid, note);
+}
+
+int syscall_group_destroy(u_int pgid) {
+	return msyscall(SYS_group_destroy, pgid);
+}
+
+int syscall_env_destroy_tree(u_int envid) {
+	return msyscall(SYS_env_destroy_tree, envid);
+}
+
+int syscall_set_teardown(u_int envid, u_int on) {
+	return msyscall(SYS_set_teardown, envid, on);
+}
+
+int syscall_ptrace(u_int req, u_int envid, void *addr, void *buf, u_int len) {
+	return msyscall(SYS_ptrace, req, envid, addr, buf, len);
+}
+
+int syscall_core_info(struct CoreInfo *info) {
+	return msyscall(SYS_core_info, info);
+}
+
+int syscall_core_read(void *buf, u_int offset, u_int len) {
+	return msyscall(SYS_core_read, buf, offset, len);
+}
+
+int syscall_core_clear(void) {
+	return msyscall(SYS_core_clear);
+}
+
+int syscall_strace(u_int envid, u_int flags) {
+	return msyscall(SYS_strace, envid, flags);
+}
+
+int syscall_strace_read(struct SyscallRecord *buf, u_int max) {
+	return msyscall(SYS_strace_read, buf, max);
+}
+
+int syscall_klog_read(char *buf, u_int len) {
+	return msyscall(SYS_klog_read, buf, len);
+}
+
+int syscall_prof_start(u_int envid) {
+	return msyscall(SYS_prof_start, envid);
+}
+
+int syscall_prof_stop(void) {
+	return msyscall(SYS_prof_stop);
+}
+
+int syscall_prof_read(struct ProfSample *buf, u_int max) {
+	return msyscall(SYS_prof_read, buf, max);
+}
+
+int syscall_tlb_read(struct TlbEntry *buf, u_int max) {
+	return msyscall(SYS_tlb_read, buf, max);
+}
+
+int syscall_memcheck(struct MemCheck *buf) {
+	return msyscall(SYS_memcheck, buf);
+}
+
+int syscall_core_wait(void) {
+	return msyscall(SYS_core_wait);
+}
@@ -1,7 +1,7 @@
 #include <env.h>
 #include <lib.h>
 void wait(u_int envid) {
-	const volatile struct Env *e;
+	const volatile struct EnvInfo *e;
 
 	e = &envs[ENVX(envid)];
 	while (e->env_id == envid && e->env_status != ENV_FREE) {
@@ -0,0 +1,18 @@
+#include <lib.h>
+
+// Check frame ref counts in the kernel, problems are described in the kernel log.
+int main(int argc, char **argv) {
+	struct MemCheck mc;
+	int r;
+
+	if ((r = syscall_memcheck(&mc)) < 0) {
+		user_panic("memcheck: %d", r);
+	}
+	printf("%d of %d frames used\n", mc.mc_used, mc.mc_frames);
+	printf("%d mismatches, %d leaks, %d double maps, %d wild ptes\n", mc.mc_mismatches,
+	       mc.mc_leaks, mc.mc_double_maps, mc.mc_wild);
+	if (r > 0) {
+		printf("see dmesg for details\n");
+	}
+	return r > 0;
+}
@@ -0,0 +1,162 @@
+#include <elf.h>
+#include <lib.h>
+
+#define MAXFUNCS 1024
+#define NSAMPLE 128
+#define NSYM 64
+
+struct Func {
+	u_int start;
+	u_int end;
+	u_int name; // offset in strtab
+	u_int hits;
+};
+
+static struct Func funcs[MAXFUNCS];
+static int nfuncs;
+static char strtab[16384];
+static u_int kernel_hits, unknown_hits, total;
+
+// Read the function symbols of the ELF file at 'path' into 'funcs'. A program without a symbol
+// table is still profiled, all its samples are unknown.
+static void load_symbols(const char *path) {
+	static Elf32_Sym syms[NSYM];
+	Elf32_Ehdr ehdr;
+	Elf32_Shdr sh, strsh;
+	int fd, i, n;
+
+	if ((fd = open(path, O_RDONLY)) < 0) {
+		user_panic("can't open %s: %d", path, fd);
+	}
+	if (readn(fd, &ehdr, sizeof ehdr) != sizeof ehdr || !elf_from(&ehdr, sizeof ehdr)) {
+		user_panic("%s is not an ELF file", path);
+	}
+	for (i = 0; i < ehdr.e_shnum; i++) {
+		seek(fd, ehdr.e_shoff + i * ehdr.e_shentsize);
+		if (readn(fd, &sh, sizeof sh) == sizeof sh && sh.sh_type == SHT_SYMTAB) {
+			break;
+		}
+	}
+	if (i == ehdr.e_shnum) {
+		printf("prof: no symbol table in %s\n", path);
+		close(fd);
+		return;
+	}
+	seek(fd, ehdr.e_shoff + sh.sh_link * ehdr.e_shentsize);
+	readn(fd, &strsh, sizeof strsh);
+	seek(fd, strsh.sh_offset);
+	readn(fd, strtab, MIN(strsh.sh_size, sizeof strtab - 1));
+
+	for (u_int off = 0; off < sh.sh_size && nfuncs < MAXFUNCS; off += n * sizeof(Elf32_Sym)) {
+		seek(fd, sh.sh_offset + off);
+		n = MIN(NSYM, (sh.sh_size - off) / sizeof(Elf32_Sym));
+		if (n == 0 || readn(fd, syms, n * sizeof(Elf32_Sym)) != n * sizeof(Elf32_Sym)) {
+			break;
+		}
+		for (i = 0; i < n && nfuncs < MAXFUNCS; i++) {
+			Elf32_Sym *s = &syms[i];
+			if (ELF32_ST_TYPE(s->st_info) != STT_FUNC || s->st_size == 0 ||
+			    s->st_name >= sizeof strtab - 1) {
+				continue;
+			}
+			funcs[nfuncs].start = s->st_value;
+			funcs[nfuncs].end = s->st_value + s->st_size;
+			funcs[nfuncs].name = s->st_name;
+			nfuncs++;
+		}
+	}
+	close(fd);
+}
+
+// Charge a sample to the function containing 'pc'.
+static void account(u_int pc) {
+	for (int i = 0; i < nfuncs; i++) {
+		if (funcs[i].start <= pc && pc < funcs[i].end) {
+			funcs[i].hits++;
+			return;
+		}
+	}
+	unknown_hits++;
+}
+
+// Account samples of 'child' taken from the kernel. Return how many were taken.
+static int collect(u_int child) {
+	static struct ProfSample samples[NSAMPLE];
+	int n;
+
+	if ((n = syscall_prof_read(samples, NSAMPLE)) < 0) {
+		user_panic("prof_read: %d", n);
+	}
+	for (int i = 0; i < n; i++) {
+		if (samples[i].ps_envid != child) {
+			continue;
+		}
+		total++;
+		if (samples[i].ps_kernel) {
+			kernel_hits++;
+		} else {
+			account(samples[i].ps_epc);
+		}
+	}
+	return n;
+}
+
+static void report_line(u_int hits, const char *name) {
+	printf("%3d%% %7d  %s\n", hits * 100 / total, hits, name);
+}
+
+// Print functions by samples, most sampled first.
+static void report(void) {
+	printf("   %%  samples  function\n");
+	if (total == 0) {
+		return;
+	}
+	for (;;) {
+		int best = -1;
+		for (int i = 0; i < nfuncs; i++) {
+			if (funcs[i].hits > 0 && (best < 0 || funcs[i].hits > funcs[best].hits)) {
+				best = i;
+			}
+		}
+		if (best < 0) {
+			break;
+		}
+		report_line(funcs[best].hits, strtab + funcs[best].name);
+		funcs[best].hits = 0;
+	}
+	if (kernel_hits) {
+		report_line(kernel_hits, "[kernel]");
+	}
+	if (unknown_hits) {
+		report_line(unknown_hits, "[unknown]");
+	}
+}
+
+// Run a command with the timer interrupt sampling it, then print a flat profile of it.
+int main(int argc, char **argv) {
+	struct EnvStat st;
+	int child, dropped;
+
+	if (argc < 2) {
+		printf("usage: prof command [args...]\n");
+		return 1;
+	}
+	load_symbols(argv[1]);
+	if ((child = spawn(argv[1], argv + 1)) < 0) {
+		user_panic("spawn %s: %d", argv[1], child);
+	}
+	syscall_prof_start(child);
+	while (syscall_env_stat(child, &st) >= 0) {
+		if (collect(child) == 0) {
+			syscall_yield();
+		}
+	}
+	dropped = syscall_prof_stop();
+	while (collect(child) > 0) {
+	}
+	if (dropped > 0) {
+		printf("prof: %d samples dropped\n", dropped);
+	}
+	report();
+	return 0;
+}
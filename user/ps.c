@@ -0,0 +1,58 @@
+#include <lib.h>
+
+#define MAXENVS 256
+
+static const char *status_name(u_int status) {
+	switch (status) {
+	case ENV_RUNNABLE:
+		return "R";
+	case ENV_NOT_RUNNABLE:
+		return "S";
+	default:
+		return "?";
+	}
+}
+
+// Print the command line of 'envid' with its arguments separated by spaces, or 'name' if it has
+// none.
+static void print_cmdline(u_int envid, const char *name) {
+	char buf[ENV_CMDLINE_MAX + 1];
+	int n = syscall_get_cmdline(envid, buf, ENV_CMDLINE_MAX);
+
+	if (n <= 0) {
+		printf("%s\n", name);
+		return;
+	}
+	if (buf[n - 1] == '\0') {
+		n--;
+	}
+	for (int i = 0; i < n; i++) {
+		if (buf[i] == '\0') {
+			buf[i] = ' ';
+		}
+	}
+	buf[n] = '\0';
+	printf("%s\n", buf);
+}
+
+int main(int argc, char **argv) {
+	static u_int ids[MAXENVS];
+	struct EnvStat st;
+	int n, r;
+
+	if ((n = syscall_env_list(ids, MAXENVS)) < 0) {
+		user_panic("env_list: %d", n);
+	}
+	printf("%8s %8s %s %3s %6s %6s %5s %-4s %s\n", "ID", "PARENT", "S", "PRI", "RUNS", "TICKS",
+	       "PAGES", "WAIT", "COMMAND");
+	for (int i = 0; i < n; i++) {
+		if ((r = syscall_env_stat(ids[i], &st)) < 0) {
+			continue;
+		}
+		printf("%08x %08x %s %3d %6d %6d %5d %-4s ", st.env_id, st.env_parent_id,
+		       status_name(st.env_status), st.env_pri, st.env_runs, st.env_ticks, st.env_pages,
+		       st.env_ipc_recving ? "ipc" : (st.env_sleeping ? "io" : "-"));
+		print_cmdline(st.env_id, st.env_name);
+	}
+	return 0;
+}
@@ -198,16 +198,6 @@ void readline(char *buf, u_int n) {
                         }
                         exit();
                 }
-                if (buf[i] == '\b' || buf[i] == 0x7f) {
-                        if (i > 0) {
-                                i -= 2;
-                        } else {
-                                i = -1;
-                        }
-                        if (buf[i] != '\b') {
-                                printf("\b");
-                        }
-                }
                 if (buf[i] == '\r' || buf[i] == '\n') {
                         buf[i] = 0;
                         return;
@@ -273,11 +263,20 @@ int main(int argc, char **argv) {
                 if ((r = fork()) < 0) {
                         user_panic("fork: %d", r);
                 }
+                // each job runs in its own process group, set by both sides to avoid a race
                 if (r == 0) {
+                        syscall_set_pgid(0, 0);
                         runcmd(buf);
                         exit();
                 } else {
+                        syscall_set_pgid(r, r);
+                        if (interactive) {
+                                syscall_tty_ioctl(TTY_SET_FG, r);
+                        }
                         wait(r);
+                        if (interactive) {
+                                syscall_tty_ioctl(TTY_SET_FG, 0);
+                        }
                 }
         }
         return 0;
@@ -0,0 +1,134 @@
+#include <lib.h>
+
+#define NRECORD 32
+
+static const char *names[MAX_SYSNO] = {
+	[SYS_putchar] = "putchar",
+	[SYS_print_cons] = "print_cons",
+	[SYS_getenvid] = "getenvid",
+	[SYS_yield] = "yield",
+	[SYS_env_destroy] = "env_destroy",
+	[SYS_set_tlb_mod_entry] = "set_tlb_mod_entry",
+	[SYS_mem_alloc] = "mem_alloc",
+	[SYS_mem_map] = "mem_map",
+	[SYS_mem_unmap] = "mem_unmap",
+	[SYS_exofork] = "exofork",
+	[SYS_set_env_status] = "set_env_status",
+	[SYS_set_trapframe] = "set_trapframe",
+	[SYS_panic] = "panic",
+	[SYS_ipc_try_send] = "ipc_try_send",
+	[SYS_ipc_recv] = "ipc_recv",
+	[SYS_cgetc] = "cgetc",
+	[SYS_write_dev] = "write_dev",
+	[SYS_read_dev] = "read_dev",
+	[SYS_shmget] = "shmget",
+	[SYS_shmat] = "shmat",
+	[SYS_shmdt] = "shmdt",
+	[SYS_shmctl] = "shmctl",
+	[SYS_semopen] = "semopen",
+	[SYS_semwait] = "semwait",
+	[SYS_sempost] = "sempost",
+	[SYS_semkill] = "semkill",
+	[SYS_console_read] = "console_read",
+	[SYS_tty_ioctl] = "tty_ioctl",
+	[SYS_env_notify] = "env_notify",
+	[SYS_set_notify_entry] = "set_notify_entry",
+	[SYS_time_of_day] = "time_of_day",
+	[SYS_set_time_of_day] = "set_time_of_day",
+	[SYS_get_trapframe] = "get_trapframe",
+	[SYS_env_list] = "env_list",
+	[SYS_env_stat] = "env_stat",
+	[SYS_getrusage] = "getrusage",
+	[SYS_get_rlimit] = "get_rlimit",
+	[SYS_set_rlimit] = "set_rlimit",
+	[SYS_set_cmdline] = "set_cmdline",
+	[SYS_get_cmdline] = "get_cmdline",
+	[SYS_set_pgid] = "set_pgid",
+	[SYS_get_pgid] = "get_pgid",
+	[SYS_set_sid] = "set_sid",
+	[SYS_get_sid] = "get_sid",
+	[SYS_group_notify] = "group_notify",
+	[SYS_group_destroy] = "group_destroy",
+	[SYS_env_destroy_tree] = "env_destroy_tree",
+	[SYS_set_teardown] = "set_teardown",
+	[SYS_ptrace] = "ptrace",
+	[SYS_core_info] = "core_info",
+	[SYS_core_read] = "core_read",
+	[SYS_core_clear] = "core_clear",
+	[SYS_strace] = "strace",
+	[SYS_strace_read] = "strace_read",
+	[SYS_klog_read] = "klog_read",
+	[SYS_prof_start] = "prof_start",
+	[SYS_prof_stop] = "prof_stop",
+	[SYS_prof_read] = "prof_read",
+	[SYS_tlb_read] = "tlb_read",
+	[SYS_memcheck] = "memcheck",
+	[SYS_core_wait] = "core_wait",
+};
+
+// Print records taken from the kernel buffer, skipping our own syscalls. Return how many were
+// taken.
+static int drain(u_int self) {
+	static struct SyscallRecord recs[NRECORD];
+	int n;
+
+	if ((n = syscall_strace_read(recs, NRECORD)) < 0) {
+		user_panic("strace_read: %d", n);
+	}
+	for (int i = 0; i < n; i++) {
+		struct SyscallRecord *r = &recs[i];
+		const char *name = r->sr_sysno < MAX_SYSNO && names[r->sr_sysno] ? names[r->sr_sysno] : "?";
+		if (r->sr_envid == self) {
+			continue;
+		}
+		printf("%6d %08x %s(%x, %x, %x, %x, %x) = %d\n", r->sr_tick, r->sr_envid, name,
+		       r->sr_args[0], r->sr_args[1], r->sr_args[2], r->sr_args[3], r->sr_args[4],
+		       r->sr_ret);
+	}
+	return n;
+}
+
+static void usage(void) {
+	printf("usage: strace [-f] command [args...]\n");
+	exit();
+}
+
+// Run a command with its syscalls recorded, and print them until it exits. With -f, the envs it
+// forks are traced too.
+int main(int argc, char **argv) {
+	struct EnvStat st;
+	u_int self = syscall_getenvid();
+	u_int flags = STRACE_RECORD;
+	int child;
+
+	ARGBEGIN {
+	case 'f':
+		flags |= STRACE_FOLLOW;
+		break;
+	default:
+		usage();
+	}
+	ARGEND
+
+	if (argc < 1) {
+		usage();
+	}
+	// the child inherits the flags through exofork
+	syscall_strace(0, flags | STRACE_FOLLOW);
+	child = spawn(argv[0], argv);
+	syscall_strace(0, 0);
+	if (child < 0) {
+		user_panic("spawn %s: %d", argv[0], child);
+	}
+	if (!(flags & STRACE_FOLLOW)) {
+		syscall_strace(child, flags);
+	}
+	while (syscall_env_stat(child, &st) >= 0) {
+		if (drain(self) == 0) {
+			syscall_yield();
+		}
+	}
+	while (drain(self) > 0) {
+	}
+	return 0;
+}
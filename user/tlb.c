@@ -0,0 +1,41 @@
+#include <lib.h>
+
+#define NTLB 64
+
+static struct TlbEntry entries[NTLB];
+
+static void print_page(u_int lo) {
+	printf(" | pfn %05x c%d%s%s%s", (lo >> 6) & 0xfffff, (lo >> 3) & 0x7, lo & 0x2 ? " V" : " -",
+	       lo & 0x4 ? " D" : "", lo & 0x1 ? " G" : "");
+}
+
+// Print the TLB entries of the cpu, only entries with a valid page unless -a is given.
+int main(int argc, char **argv) {
+	int all = 0, n;
+
+	ARGBEGIN {
+	case 'a':
+		all = 1;
+		break;
+	default:
+		printf("usage: tlb [-a]\n");
+		return 1;
+	}
+	ARGEND
+
+	if ((n = syscall_tlb_read(entries, NTLB)) < 0) {
+		user_panic("tlb_read: %d", n);
+	}
+	for (int i = 0; i < n; i++) {
+		struct TlbEntry *e = &entries[i];
+		if (!all && !((e->te_lo[0] | e->te_lo[1]) & 0x2)) {
+			continue;
+		}
+		printf("%2d va %08x asid %02x mask %x", i, e->te_hi & ~0x1fff, e->te_hi & 0xff,
+		       e->te_pagemask);
+		print_page(e->te_lo[0]);
+		print_page(e->te_lo[1]);
+		printf("\n");
+	}
+	return 0;
+}